@@ -0,0 +1,204 @@
+use std::fmt;
+
+/// Command-line options of the packaged app, e.g. `ytadmin --profile uat --devtools`.
+#[derive(Debug, Default, Clone)]
+pub struct AppArgs {
+    /// Replaces the URL of the first window.
+    pub url: Option<String>,
+    /// Profile used for this launch instead of the remembered one.
+    pub profile: Option<String>,
+    pub devtools: bool,
+    /// Wipe webview data and saved window state before starting.
+    pub reset: bool,
+    /// Print the effective config as JSON, secrets masked, and exit without opening a window.
+    pub print_config: bool,
+    /// Default level from `--log-level LEVEL`.
+    pub log_level: Option<String>,
+    /// `(source or target, level)` from `--log-level SCOPE=LEVEL`, in order.
+    pub log_level_overrides: Vec<(String, String)>,
+    /// Positional arguments: URLs to open or files to hand to the page.
+    pub targets: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum ArgsError {
+    Help,
+    Version,
+    Invalid(String),
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgsError::Help => write!(f, "help requested"),
+            ArgsError::Version => write!(f, "version requested"),
+            ArgsError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+pub const LOG_LEVELS: [&str; 5] = ["trace", "debug", "info", "warn", "error"];
+
+impl AppArgs {
+    /// Parses arguments without the program name. Accepts both `--url X` and `--url=X`.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, ArgsError> {
+        let mut parsed = AppArgs::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            // macOS adds a process serial number when launched from Finder on older versions.
+            if arg.starts_with("-psn_") {
+                continue;
+            }
+
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => (name.to_string(), Some(value.to_string())),
+                _ => (arg.clone(), None),
+            };
+            let mut value = |name: &str| {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .filter(|value| !value.is_empty())
+                    .ok_or_else(|| ArgsError::Invalid(format!("{} requires a value", name)))
+            };
+
+            match name.as_str() {
+                "-h" | "--help" => return Err(ArgsError::Help),
+                "-V" | "--version" => return Err(ArgsError::Version),
+                "--url" => parsed.url = Some(value("--url")?),
+                "--profile" => parsed.profile = Some(value("--profile")?),
+                "--log-level" => {
+                    // `info`, `network=warn` or several of them: `info,webview=debug,reqwest=trace`
+                    for directive in value("--log-level")?.split(',').filter(|d| !d.is_empty()) {
+                        let (scope, level) = match directive.rsplit_once('=') {
+                            Some((scope, level)) => (Some(scope), level.to_lowercase()),
+                            None => (None, directive.to_lowercase()),
+                        };
+                        if !LOG_LEVELS.contains(&level.as_str()) {
+                            return Err(ArgsError::Invalid(format!(
+                                "--log-level must be one of {}, optionally as SOURCE=LEVEL or TARGET=LEVEL",
+                                LOG_LEVELS.join(", ")
+                            )));
+                        }
+                        match scope {
+                            Some(scope) if !scope.is_empty() => {
+                                parsed.log_level_overrides.push((scope.to_string(), level))
+                            }
+                            Some(_) => return Err(ArgsError::Invalid(format!("invalid --log-level: {}", directive))),
+                            None => parsed.log_level = Some(level),
+                        }
+                    }
+                }
+                "--devtools" if cfg!(any(debug_assertions, feature = "devtools")) => parsed.devtools = true,
+                "--devtools" => {
+                    return Err(ArgsError::Invalid(
+                        "--devtools is only available in debug builds or with the devtools feature".to_string(),
+                    ))
+                }
+                "--reset" => parsed.reset = true,
+                "--print-config" => parsed.print_config = true,
+                _ if !arg.starts_with('-') => parsed.targets.push(arg),
+                _ => return Err(ArgsError::Invalid(format!("unknown argument: {}", arg))),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+pub fn usage(program: &str) -> String {
+    format!(
+        "Usage: {} [OPTIONS] [URL | FILE]...
+
+Options:
+      --url <URL>            Open URL in the main window instead of the configured one
+      --profile <NAME>       Use the named profile for this launch
+      --devtools             Open the web inspector for every window (debug builds only)
+      --reset                Wipe webview data and saved window state before starting
+      --print-config         Print the effective config as JSON, secrets masked, and exit
+      --log-level <LEVELS>   Minimum log level: {}; per source (rust, webview,
+                             network) or target as SCOPE=LEVEL, comma-separated
+  -h, --help                 Print help
+  -V, --version              Print version",
+        program,
+        LOG_LEVELS.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<AppArgs, ArgsError> {
+        AppArgs::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_url_and_profile_in_both_forms() {
+        let args = parse(&["--url", "https://example.com", "--profile=uat"]).unwrap();
+        assert_eq!(args.url.as_deref(), Some("https://example.com"));
+        assert_eq!(args.profile.as_deref(), Some("uat"));
+
+        let args = parse(&["--url=https://example.com/?a=b", "--profile", "prod"]).unwrap();
+        assert_eq!(args.url.as_deref(), Some("https://example.com/?a=b"));
+        assert_eq!(args.profile.as_deref(), Some("prod"));
+    }
+
+    #[test]
+    fn missing_value_is_an_error() {
+        assert!(matches!(parse(&["--url"]), Err(ArgsError::Invalid(_))));
+        assert!(matches!(parse(&["--profile="]), Err(ArgsError::Invalid(_))));
+    }
+
+    #[test]
+    fn parses_log_level_directives() {
+        let args = parse(&["--log-level", "INFO,network=warn,app_lib::app::invoke=debug"]).unwrap();
+        assert_eq!(args.log_level.as_deref(), Some("info"));
+        assert_eq!(
+            args.log_level_overrides,
+            vec![
+                ("network".to_string(), "warn".to_string()),
+                ("app_lib::app::invoke".to_string(), "debug".to_string()),
+            ]
+        );
+
+        let args = parse(&["--log-level=webview=trace"]).unwrap();
+        assert_eq!(args.log_level, None);
+        assert_eq!(args.log_level_overrides, vec![("webview".to_string(), "trace".to_string())]);
+    }
+
+    #[test]
+    fn rejects_invalid_log_levels() {
+        assert!(matches!(parse(&["--log-level", "loud"]), Err(ArgsError::Invalid(_))));
+        assert!(matches!(parse(&["--log-level", "network=loud"]), Err(ArgsError::Invalid(_))));
+        assert!(matches!(parse(&["--log-level", "=info"]), Err(ArgsError::Invalid(_))));
+    }
+
+    #[test]
+    fn collects_positional_targets_and_skips_psn() {
+        let args = parse(&["-psn_0_12345", "https://example.com/a", "--reset", "report.pdf"]).unwrap();
+        assert_eq!(args.targets, vec!["https://example.com/a", "report.pdf"]);
+        assert!(args.reset);
+    }
+
+    #[test]
+    fn unknown_flags_help_and_version() {
+        match parse(&["--verbose"]) {
+            Err(ArgsError::Invalid(message)) => assert!(message.contains("--verbose")),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(parse(&["-h"]), Err(ArgsError::Help)));
+        assert!(matches!(parse(&["--version"]), Err(ArgsError::Version)));
+    }
+
+    #[test]
+    fn devtools_follows_build() {
+        let result = parse(&["--devtools"]);
+        if cfg!(any(debug_assertions, feature = "devtools")) {
+            assert!(result.unwrap().devtools);
+        } else {
+            assert!(matches!(result, Err(ArgsError::Invalid(_))));
+        }
+    }
+}
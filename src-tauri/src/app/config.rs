@@ -1,6 +1,19 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Label of the window built from the first `windows` entry.
+pub const MAIN_WINDOW_LABEL: &str = "pake";
+
+/// Window labels follow the order of `PakeConfig.windows`: `pake`, `pake-1`, `pake-2`, ...
+pub fn window_label(index: usize) -> String {
+    if index == 0 {
+        MAIN_WINDOW_LABEL.to_string()
+    } else {
+        format!("{}-{}", MAIN_WINDOW_LABEL, index)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WindowConfig {
     pub url: String,
     pub hide_title_bar: bool,
@@ -15,7 +28,28 @@ pub struct WindowConfig {
     pub activation_shortcut: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+pub const DEFAULT_WINDOW_WIDTH: f64 = 1200.0;
+pub const DEFAULT_WINDOW_HEIGHT: f64 = 780.0;
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            hide_title_bar: false,
+            fullscreen: false,
+            width: DEFAULT_WINDOW_WIDTH,
+            height: DEFAULT_WINDOW_HEIGHT,
+            resizable: true,
+            url_type: "web".to_string(),
+            always_on_top: false,
+            dark_mode: false,
+            disabled_web_shortcuts: false,
+            activation_shortcut: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PlatformSpecific<T> {
     pub macos: T,
     pub linux: T,
@@ -47,17 +81,427 @@ where
 pub type UserAgent = PlatformSpecific<String>;
 pub type FunctionON = PlatformSpecific<bool>;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A named environment such as prod or UAT. Empty fields keep the top-level value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileConfig {
+    pub name: String,
+    /// Text shown in the tray menu, defaults to `name`.
+    #[serde(default)]
+    pub title: String,
+    /// Replaces the URL of the first window.
+    pub url: String,
+    #[serde(default)]
+    pub system_tray_path: String,
+    #[serde(default)]
+    pub proxy_url: String,
+    /// Used on every platform when set.
+    #[serde(default)]
+    pub user_agent: String,
+}
+
+impl ProfileConfig {
+    pub fn display_name(&self) -> &str {
+        if self.title.is_empty() {
+            &self.name
+        } else {
+            &self.title
+        }
+    }
+}
+
+/// An `inject` entry in pake.json.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum InjectEntry {
+    /// Plain path written by the CLI, already bundled into `inject/custom.js` at build time.
+    Bundled(String),
+    /// Loaded at startup, so scripts can be fixed without rebuilding.
+    Runtime(InjectRule),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InjectRule {
+    /// Absolute, or relative to the app config dir and then the resource dir.
+    pub path: String,
+    /// Inferred from the file extension when omitted.
+    #[serde(default, rename = "type")]
+    pub kind: Option<InjectKind>,
+    /// URL patterns where `*` matches anything; empty means every page.
+    #[serde(default)]
+    pub matches: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InjectKind {
+    Js,
+    Css,
+}
+
+/// Links like `ytadmin://orders/42?tab=log` opened inside the app instead of a browser.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeepLinkConfig {
+    /// URL scheme to register; empty disables deep links.
+    #[serde(default)]
+    pub scheme: String,
+    /// The link path is appended to this URL; defaults to the origin of the first window.
+    #[serde(default)]
+    pub base_url: String,
+    /// Path patterns such as `/orders/*` that may be opened; empty allows every path.
+    #[serde(default)]
+    pub allowed_paths: Vec<String>,
+}
+
+/// Log files written next to the in-memory buffer, under the app log dir.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogFileConfig {
+    pub enabled: bool,
+    /// A new file is started once the current one reaches this many bytes.
+    pub max_file_size: u64,
+    /// Oldest files beyond this count are deleted.
+    pub max_files: usize,
+    /// Files not modified for this many days are deleted; 0 keeps them.
+    pub retention_days: u32,
+}
+
+impl Default for LogFileConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_file_size: 5 * 1024 * 1024,
+            max_files: 20,
+            retention_days: 7,
+        }
+    }
+}
+
+/// Minimum level of recorded entries, with overrides per source and per target. Entries
+/// below it reach neither the buffer nor the log files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogLevelConfig {
+    /// `trace`, `debug`, `info`, `warn` or `error`.
+    pub level: String,
+    /// Per source: `rust`, `webview` (console) or `network` (XHR / fetch).
+    pub sources: BTreeMap<String, String>,
+    /// Per target: a Rust module path such as `app_lib::app::log_upload` or `reqwest`, or the
+    /// label of the window page entries came from. The longest matching path wins, and takes
+    /// precedence over `sources`.
+    pub targets: BTreeMap<String, String>,
+}
+
+impl Default for LogLevelConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            sources: BTreeMap::new(),
+            targets: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyslogProtocol {
+    #[default]
+    Udp,
+    /// Octet-counted frames (RFC 6587).
+    Tcp,
+    /// TCP wrapped in TLS (RFC 5425).
+    Tls,
+}
+
+/// Forwards every recorded entry to a syslog collector as RFC 5424 messages.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyslogConfig {
+    /// `host:port` of the collector; empty disables forwarding.
+    pub address: String,
+    pub protocol: SyslogProtocol,
+    /// PEM file with the CA certificates trusted for `tls`, absolute or relative to the app
+    /// config dir. Empty trusts the Mozilla root store.
+    pub ca_file: String,
+    /// Syslog facility code, 0-23; 1 is `user`, 16-23 are `local0`-`local7`.
+    pub facility: u8,
+    /// Entries below this level are not forwarded, on top of `log_level`.
+    pub min_level: String,
+    /// Entries waiting to be sent; further ones are dropped and counted while the collector
+    /// is slow or unreachable.
+    pub queue_size: usize,
+}
+
+impl Default for SyslogConfig {
+    fn default() -> Self {
+        Self {
+            address: String::new(),
+            protocol: SyslogProtocol::default(),
+            ca_file: String::new(),
+            facility: 1,
+            min_level: "info".to_string(),
+            queue_size: 1000,
+        }
+    }
+}
+
+impl SyslogConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.address.is_empty()
+    }
+}
+
+/// Exports log entries and command spans to an OpenTelemetry collector over OTLP/HTTP (JSON).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OtlpConfig {
+    /// Base URL of the collector, e.g. `http://localhost:4318`; `/v1/logs` and `/v1/traces`
+    /// are appended. Empty disables export.
+    pub endpoint: String,
+    /// Extra request headers, e.g. for authentication.
+    pub headers: BTreeMap<String, String>,
+    pub logs: bool,
+    /// Spans for `download_file`, `handle_log_upload` and `send_notification`.
+    pub traces: bool,
+    /// Entries below this level are not exported, on top of `log_level`.
+    pub min_level: String,
+    /// Records per request.
+    pub batch_size: usize,
+    /// Seconds after which a partial batch is sent anyway.
+    pub export_interval: u64,
+    /// Records waiting to be exported; further ones are dropped and counted.
+    pub queue_size: usize,
+}
+
+impl Default for OtlpConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: String::new(),
+            headers: BTreeMap::new(),
+            logs: true,
+            traces: true,
+            min_level: "info".to_string(),
+            batch_size: 512,
+            export_interval: 5,
+            queue_size: 2048,
+        }
+    }
+}
+
+impl OtlpConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.endpoint.is_empty() && (self.logs || self.traces)
+    }
+}
+
+/// Rules applied to every log entry when it is recorded and again to uploaded logs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactionConfig {
+    pub enabled: bool,
+    /// Built-in rules to turn off: `bearer`, `cookie`, `email`, `phone`, `query_secret`.
+    pub disabled_rules: Vec<String>,
+    /// Extra rules, applied after the built-in ones.
+    pub rules: Vec<RedactionRule>,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            disabled_rules: Vec::new(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionRule {
+    pub name: String,
+    /// Regex in `regex` crate syntax.
+    pub pattern: String,
+    /// May refer to capture groups as `$1` or `${name}`.
+    #[serde(default = "default_redaction_replacement")]
+    pub replacement: String,
+}
+
+fn default_redaction_replacement() -> String {
+    "[REDACTED]".to_string()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UploadBackendKind {
+    /// POST to `endpoint` for a presigned URL, then PUT the zip there.
+    #[default]
+    Presigned,
+    /// Send the zip as the request body to `endpoint` with `method`.
+    Http,
+    /// `multipart/form-data` POST to `endpoint`, the zip in `form_field`.
+    Multipart,
+    /// PUT into the WebDAV collection at `endpoint`.
+    Webdav,
+    /// Copy the zip into `directory`.
+    Local,
+}
+
+/// Where "上传日志" sends the bundle. Uploading is disabled until the backend has a target.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogUploadConfig {
+    pub backend: UploadBackendKind,
+    /// Presign API, upload URL or WebDAV collection depending on `backend`.
+    pub endpoint: String,
+    /// `PUT` or `POST`, for the `http` backend.
+    pub method: String,
+    /// Form field carrying the zip, for the `multipart` backend.
+    pub form_field: String,
+    /// Target directory of the `local` backend, absolute or relative to the app config dir.
+    pub directory: String,
+    /// Header carrying the API key.
+    pub auth_header: String,
+    /// Name of an environment variable holding the key; checked first.
+    pub api_key_env: String,
+    /// File containing the key, absolute or relative to the app config dir.
+    pub api_key_file: String,
+    /// Literal key, meant for `pake.override.json` rather than the bundled pake.json.
+    pub api_key: String,
+    /// Object key prefix in the bucket.
+    pub path_prefix: String,
+    /// Lifetime of the presigned URL in seconds.
+    pub expiration: u32,
+    /// age X25519 public key (`age1...`). When set, bundles are encrypted before they touch
+    /// the disk and uploaded as `*.zip.age`.
+    pub recipient: String,
+    /// Bundles larger than this many bytes are uploaded as `name.zip.001`, `.002`, ...; 0 disables splitting.
+    pub max_part_size: u64,
+}
+
+impl Default for LogUploadConfig {
+    fn default() -> Self {
+        Self {
+            backend: UploadBackendKind::default(),
+            endpoint: String::new(),
+            method: "PUT".to_string(),
+            form_field: "file".to_string(),
+            directory: String::new(),
+            auth_header: "X-API-Key".to_string(),
+            api_key_env: String::new(),
+            api_key_file: String::new(),
+            api_key: String::new(),
+            path_prefix: "upload-log/".to_string(),
+            expiration: 3600,
+            recipient: String::new(),
+            max_part_size: 50 * 1024 * 1024,
+        }
+    }
+}
+
+impl LogUploadConfig {
+    pub fn is_enabled(&self) -> bool {
+        match self.backend {
+            UploadBackendKind::Local => !self.directory.is_empty(),
+            _ => !self.endpoint.is_empty(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PakeConfig {
     pub windows: Vec<WindowConfig>,
     pub user_agent: UserAgent,
     pub system_tray: FunctionON,
     pub system_tray_path: String,
     pub proxy_url: String,
+    #[serde(default)]
+    pub inject: Vec<InjectEntry>,
+    #[serde(default)]
+    pub deep_link: DeepLinkConfig,
+    #[serde(default)]
+    pub log_level: LogLevelConfig,
+    #[serde(default)]
+    pub log_file: LogFileConfig,
+    #[serde(default)]
+    pub log_upload: LogUploadConfig,
+    #[serde(default)]
+    pub syslog: SyslogConfig,
+    #[serde(default)]
+    pub otlp: OtlpConfig,
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<ProfileConfig>,
+    /// Profile used until the user picks another one from the tray.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub default_profile: String,
+}
+
+impl Default for PakeConfig {
+    fn default() -> Self {
+        Self {
+            windows: vec![WindowConfig::default()],
+            user_agent: UserAgent::default(),
+            system_tray: FunctionON {
+                macos: true,
+                linux: true,
+                windows: true,
+            },
+            system_tray_path: String::new(),
+            proxy_url: String::new(),
+            inject: Vec::new(),
+            deep_link: DeepLinkConfig::default(),
+            log_level: LogLevelConfig::default(),
+            log_file: LogFileConfig::default(),
+            log_upload: LogUploadConfig::default(),
+            syslog: SyslogConfig::default(),
+            otlp: OtlpConfig::default(),
+            redaction: RedactionConfig::default(),
+            profiles: Vec::new(),
+            default_profile: String::new(),
+        }
+    }
 }
 
 impl PakeConfig {
     pub fn show_system_tray(&self) -> bool {
         self.system_tray.copied()
     }
+
+    pub fn profile(&self, name: &str) -> Option<&ProfileConfig> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    /// Overrides the first window URL, tray icon, proxy and user agent with the named profile.
+    pub fn apply_profile(&mut self, name: &str) {
+        let Some(profile) = self.profile(name).cloned() else {
+            return;
+        };
+
+        if let Some(window) = self.windows.first_mut() {
+            window.url = profile.url;
+            window.url_type = "web".to_string();
+        }
+        if !profile.system_tray_path.is_empty() {
+            self.system_tray_path = profile.system_tray_path;
+        }
+        if !profile.proxy_url.is_empty() {
+            self.proxy_url = profile.proxy_url;
+        }
+        if !profile.user_agent.is_empty() {
+            self.user_agent = UserAgent {
+                macos: profile.user_agent.clone(),
+                linux: profile.user_agent.clone(),
+                windows: profile.user_agent,
+            };
+        }
+    }
+
+    /// Activation shortcuts paired with the label of the window they toggle.
+    pub fn activation_shortcuts(&self) -> Vec<(String, String)> {
+        self.windows
+            .iter()
+            .enumerate()
+            .filter(|(_, window)| !window.activation_shortcut.is_empty())
+            .map(|(index, window)| (window_label(index), window.activation_shortcut.clone()))
+            .collect()
+    }
 }
@@ -0,0 +1,251 @@
+use crate::app::config::PakeConfig;
+use crate::app::invoke::{
+    add_log_entry, send_notification, try_push_log_entry, LogFields, LogLevel, LogSource,
+    NotificationParams,
+};
+use crate::app::log_export::export_bundle;
+use crate::app::log_file::{flush_log_file, recent_log_lines};
+use crate::app::log_upload::{upload_bundle, BundleJob, LogsPayload};
+use crate::util::get_main_window;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::backtrace::Backtrace;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+const CRASH_DIR: &str = "crashes";
+const CRASH_FILE_PREFIX: &str = "crash-";
+/// Renamed to this once the user has been asked about it.
+const HANDLED_SUFFIX: &str = ".handled.json";
+const MAX_CRASH_FILES: usize = 10;
+const RECENT_LOG_LINES: usize = 5000;
+
+// 崩溃文件目录，在日志目录确定后设置；之前的崩溃写入按标识符推算出的同一目录
+static CRASH_DIR_PATH: once_cell::sync::OnceCell<PathBuf> = once_cell::sync::OnceCell::new();
+
+/// Written to `crashes/crash-<time>-<pid>.json` in the log dir when a panic happens.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub timestamp: String,
+    pub message: String,
+    /// `file:line:column` of the panic.
+    pub location: Option<String>,
+    pub thread: String,
+    pub backtrace: String,
+    pub version: String,
+    pub os: String,
+    pub arch: String,
+}
+
+impl CrashReport {
+    fn save(&self, dir: &Path) -> std::io::Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!(
+            "{}{}-{}.json",
+            CRASH_FILE_PREFIX,
+            Local::now().format("%Y%m%d-%H%M%S"),
+            std::process::id()
+        ));
+        let json = serde_json::to_vec_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(&path, json)?;
+        Ok(path)
+    }
+
+    /// Skipped when the buffer is busy; the crash file has the report either way.
+    fn record(&self) {
+        let mut fields = LogFields::new();
+        fields.insert("location".to_string(), self.location.clone().into());
+        fields.insert("thread".to_string(), self.thread.clone().into());
+        fields.insert("backtrace".to_string(), self.backtrace.clone().into());
+        let recorded = try_push_log_entry(
+            LogLevel::Error,
+            LogSource::Rust,
+            "pake::crash",
+            format!("程序崩溃: {}", self.message),
+            fields,
+        );
+        if !recorded {
+            eprintln!("日志缓冲区被占用，崩溃信息只写入崩溃文件");
+        }
+    }
+}
+
+/// Records every panic into the log buffer and a crash file, then hands it on to the
+/// previous hook. Installed right after the config is loaded; until [`init_crash_dir`] runs,
+/// crash files go to the log dir derived from `identifier`.
+pub fn install_panic_hook(version: String, identifier: &str) {
+    let early_dir = default_log_dir(identifier).map(|dir| dir.join(CRASH_DIR));
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        let report = CrashReport {
+            timestamp: Local::now().to_rfc3339(),
+            message,
+            location: info.location().map(|location| location.to_string()),
+            thread: std::thread::current().name().unwrap_or("<unnamed>").to_string(),
+            backtrace: Backtrace::force_capture().to_string(),
+            version: version.clone(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+        };
+
+        // 先写崩溃文件，不依赖任何日志锁
+        match CRASH_DIR_PATH.get().or(early_dir.as_ref()) {
+            Some(dir) => {
+                if let Err(e) = report.save(dir) {
+                    eprintln!("写入崩溃文件失败: {}", e);
+                }
+            }
+            None => eprintln!("无法确定崩溃文件目录，崩溃信息: {:?}", report),
+        }
+        report.record();
+        // 进程可能随即退出，先让队列里的日志落盘
+        flush_log_file();
+        previous(info);
+    }));
+}
+
+/// Same location as `app.path().app_log_dir()`, known before the app is built.
+fn default_log_dir(identifier: &str) -> Option<PathBuf> {
+    #[cfg(target_os = "macos")]
+    let dir = dirs::home_dir().map(|dir| dir.join("Library/Logs").join(identifier));
+
+    #[cfg(not(target_os = "macos"))]
+    let dir = dirs::data_local_dir().map(|dir| dir.join(identifier).join("logs"));
+
+    dir
+}
+
+/// Where crash files go from now on; also drops the oldest beyond [`MAX_CRASH_FILES`].
+pub fn init_crash_dir(log_dir: &Path) {
+    let dir = log_dir.join(CRASH_DIR);
+    let mut files = crash_files(&dir);
+    if files.len() > MAX_CRASH_FILES {
+        for path in files.drain(..files.len() - MAX_CRASH_FILES) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+    let _ = CRASH_DIR_PATH.set(dir);
+}
+
+/// Crash files in `dir`, oldest first. Names embed the time, so they sort chronologically.
+fn crash_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(CRASH_FILE_PREFIX) && name.ends_with(".json"))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+fn pending_crash_files(dir: &Path) -> Vec<PathBuf> {
+    crash_files(dir)
+        .into_iter()
+        .filter(|path| !path.to_string_lossy().ends_with(HANDLED_SUFFIX))
+        .collect()
+}
+
+fn mark_handled(paths: &[PathBuf]) {
+    for path in paths {
+        let handled = path.with_extension("").with_extension(&HANDLED_SUFFIX[1..]);
+        if let Err(e) = std::fs::rename(path, &handled) {
+            add_log_entry("WARN", &format!("无法标记崩溃文件 {:?}: {}", path, e));
+        }
+    }
+}
+
+/// If the previous run crashed, asks whether to send the crash report with the recent logs:
+/// uploaded when `log_upload` is configured, otherwise saved through the export dialog.
+pub fn check_previous_crash(app: &AppHandle) {
+    let Some(dir) = CRASH_DIR_PATH.get() else {
+        return;
+    };
+    let pending = pending_crash_files(dir);
+    let Some(latest) = pending.last() else {
+        return;
+    };
+    let message = std::fs::read(latest)
+        .ok()
+        .and_then(|content| serde_json::from_slice::<CrashReport>(&content).ok())
+        .map(|report| report.message)
+        .unwrap_or_default();
+    add_log_entry("WARN", &format!("检测到上次运行崩溃: {:?}", latest));
+
+    let upload_enabled = app.state::<PakeConfig>().log_upload.is_enabled();
+    let send_label = if upload_enabled { "发送报告" } else { "导出报告" };
+    let app = app.clone();
+    app.dialog()
+        .message(format!(
+            "应用上次异常退出：{}\n\n是否{}崩溃报告和最近的日志，帮助我们排查问题？",
+            message, send_label
+        ))
+        .title("应用上次异常退出")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            send_label.to_string(),
+            "不发送".to_string(),
+        ))
+        .show(move |send| {
+            if !send {
+                add_log_entry("INFO", "用户选择不发送崩溃报告");
+                mark_handled(&pending);
+                return;
+            }
+            tauri::async_runtime::spawn(async move {
+                let result = send_crash_report(&app, &pending, upload_enabled).await;
+                mark_handled(&pending);
+                let (title, body) = match result {
+                    Ok(Some(detail)) => ("崩溃报告已提交".to_string(), detail),
+                    Ok(None) => return,
+                    Err(e) => {
+                        add_log_entry("ERROR", &format!("发送崩溃报告失败: {}", e));
+                        ("发送崩溃报告失败".to_string(), e)
+                    }
+                };
+                if let Some(window) = get_main_window(&app) {
+                    let _ = send_notification(app.clone(), window, NotificationParams::new(&title, &body));
+                }
+            });
+        });
+}
+
+/// Bundles the crash files with the tail of the log files. Returns what to tell the user,
+/// or `None` when the export dialog was cancelled.
+async fn send_crash_report(
+    app: &AppHandle,
+    crash_files: &[PathBuf],
+    upload: bool,
+) -> Result<Option<String>, String> {
+    let config = app.state::<PakeConfig>();
+    let log_dir = app.path().app_log_dir().map_err(|e| e.to_string())?;
+    let logs = LogsPayload::from_file_lines(recent_log_lines(&log_dir, RECENT_LOG_LINES));
+    let mut job = BundleJob::prepare(app, &config, logs, None)?;
+    for path in crash_files {
+        let data = std::fs::read(path).map_err(|e| format!("无法读取崩溃文件 {:?}: {}", path, e))?;
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        job.add_file(format!("{}/{}", CRASH_DIR, name), data);
+    }
+
+    if upload {
+        let result = upload_bundle(app, &config.log_upload, job, None).await?;
+        Ok(Some(format!("已上传 {}", result.upload_id)))
+    } else {
+        Ok(export_bundle(app, job)
+            .await?
+            .map(|path| format!("已保存到 {}", path.display())))
+    }
+}
@@ -0,0 +1,270 @@
+use crate::app::config::{DeepLinkConfig, PakeConfig};
+use crate::app::invoke::add_log_entry;
+use crate::util::wildcard_match;
+use tauri::{AppHandle, Config, Url};
+
+/// Maps `<scheme>://orders/42?tab=log` onto `<base_url>/orders/42?tab=log`.
+pub fn resolve_deep_link(config: &PakeConfig, link: &Url) -> Result<Url, String> {
+    let deep_link = &config.deep_link;
+    if deep_link.scheme.is_empty() || !link.scheme().eq_ignore_ascii_case(&deep_link.scheme) {
+        return Err(format!("未配置的协议: {}", link.scheme()));
+    }
+
+    let base = base_url(config)?;
+
+    // `scheme://orders/42` parses `orders` as the host, `scheme:///orders/42` has none.
+    let path = match link.host_str() {
+        Some(host) if !host.is_empty() => format!("/{}{}", host, link.path()),
+        _ => link.path().to_string(),
+    };
+    let path = if path.starts_with('/') {
+        path
+    } else {
+        format!("/{}", path)
+    };
+
+    let mut target = format!("{}{}", base.as_str().trim_end_matches('/'), path);
+    if let Some(query) = link.query() {
+        target.push('?');
+        target.push_str(query);
+    }
+    if let Some(fragment) = link.fragment() {
+        target.push('#');
+        target.push_str(fragment);
+    }
+    let target = Url::parse(&target).map_err(|e| e.to_string())?;
+
+    // Check the normalised path, so `/orders/../admin` cannot sneak past `/orders/*`, and
+    // only accept whole segments, so `/app` does not cover `/app-admin`.
+    let relative_path = target
+        .path()
+        .strip_prefix(base.path().trim_end_matches('/'))
+        .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+        .filter(|_| target.origin() == base.origin())
+        .ok_or_else(|| format!("跳转地址超出了基础地址: {}", target))?;
+    let relative_path = if relative_path.starts_with('/') {
+        relative_path.to_string()
+    } else {
+        format!("/{}", relative_path)
+    };
+
+    if !deep_link.allowed_paths.is_empty()
+        && !deep_link
+            .allowed_paths
+            .iter()
+            .any(|pattern| wildcard_match(pattern, &relative_path))
+    {
+        return Err(format!("路径不在允许列表中: {}", relative_path));
+    }
+
+    Ok(target)
+}
+
+fn base_url(config: &PakeConfig) -> Result<Url, String> {
+    if !config.deep_link.base_url.is_empty() {
+        return Url::parse(&config.deep_link.base_url).map_err(|e| e.to_string());
+    }
+
+    let window = config
+        .windows
+        .first()
+        .filter(|window| window.url_type == "web")
+        .ok_or_else(|| "主窗口不是网页地址，无法确定基础地址".to_string())?;
+    let url = Url::parse(&window.url).map_err(|e| e.to_string())?;
+    Url::parse(&url.origin().ascii_serialization()).map_err(|e| e.to_string())
+}
+
+/// Points the OS at this executable for `deep_link.scheme`. macOS reads the scheme from
+/// `CFBundleURLTypes` in info.plist instead, so there is nothing to do at runtime there.
+pub fn register_scheme(_app: &AppHandle, deep_link: &DeepLinkConfig, _tauri_config: &Config) {
+    if deep_link.scheme.is_empty() {
+        return;
+    }
+
+    let scheme = deep_link.scheme.clone();
+    #[cfg(any(target_os = "linux", target_os = "windows"))]
+    let identifier = _tauri_config.identifier.clone();
+
+    // Registration shells out, keep it off the startup path.
+    std::thread::spawn(move || {
+        #[cfg(target_os = "linux")]
+        let result = register_linux(&scheme, &identifier);
+        #[cfg(target_os = "windows")]
+        let result = register_windows(&scheme, &identifier);
+        #[cfg(target_os = "macos")]
+        let result: Result<bool, String> = Ok(false);
+
+        match result {
+            Ok(true) => add_log_entry("INFO", &format!("已注册链接协议: {}://", scheme)),
+            Ok(false) => {}
+            Err(e) => add_log_entry("ERROR", &format!("注册链接协议失败: {}:// - {}", scheme, e)),
+        }
+    });
+}
+
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+fn executable_path() -> Result<String, String> {
+    // AppImages run from a temporary mount, register the image itself.
+    #[cfg(target_os = "linux")]
+    if let Ok(appimage) = std::env::var("APPIMAGE") {
+        return Ok(appimage);
+    }
+
+    std::env::current_exe()
+        .map(|path| path.to_string_lossy().into_owned())
+        .map_err(|e| e.to_string())
+}
+
+/// Writes the handler entry and makes it the default, skipping whatever is already in
+/// place. Returns whether anything changed.
+#[cfg(target_os = "linux")]
+fn register_linux(scheme: &str, identifier: &str) -> Result<bool, String> {
+    let applications_dir = dirs::data_dir()
+        .ok_or("无法获取数据目录")?
+        .join("applications");
+    std::fs::create_dir_all(&applications_dir).map_err(|e| e.to_string())?;
+
+    let desktop_name = format!("{}-handler.desktop", identifier);
+    let desktop_path = applications_dir.join(&desktop_name);
+    let desktop_entry = format!(
+        "[Desktop Entry]\nType=Application\nName={}\nExec=\"{}\" %u\nMimeType=x-scheme-handler/{};\nNoDisplay=true\nTerminal=false\n",
+        identifier,
+        executable_path()?,
+        scheme
+    );
+    let mut changed = false;
+    if std::fs::read_to_string(&desktop_path).ok().as_deref() != Some(desktop_entry.as_str()) {
+        std::fs::write(&desktop_path, desktop_entry).map_err(|e| e.to_string())?;
+        changed = true;
+    }
+
+    let mime_type = format!("x-scheme-handler/{}", scheme);
+    let current = run_command("xdg-mime", &["query", "default", &mime_type]).unwrap_or_default();
+    if current.trim() != desktop_name {
+        run_command("xdg-mime", &["default", &desktop_name, &mime_type])?;
+        changed = true;
+    }
+    Ok(changed)
+}
+
+/// Writes the `HKCU` class keys unless they already point at this executable. Returns
+/// whether anything changed.
+#[cfg(target_os = "windows")]
+fn register_windows(scheme: &str, identifier: &str) -> Result<bool, String> {
+    let key = format!(r"HKCU\Software\Classes\{}", scheme);
+    let command_key = format!(r"{}\shell\open\command", key);
+    let command = format!("\"{}\" \"%1\"", executable_path()?);
+
+    let current = run_command("reg", &["query", &command_key, "/ve"]).unwrap_or_default();
+    if current.lines().any(|line| line.trim_end().ends_with(&command)) {
+        return Ok(false);
+    }
+
+    run_command("reg", &["add", &key, "/ve", "/d", &format!("URL:{} Protocol", identifier), "/f"])?;
+    run_command("reg", &["add", &key, "/v", "URL Protocol", "/d", "", "/f"])?;
+    run_command("reg", &["add", &command_key, "/ve", "/d", &command, "/f"])?;
+    Ok(true)
+}
+
+/// Runs `program` and returns its stdout.
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+fn run_command(program: &str, args: &[&str]) -> Result<String, String> {
+    let mut command = std::process::Command::new(program);
+    command.args(args);
+
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    let output = command.output().map_err(|e| format!("{}: {}", program, e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(format!(
+            "{} exited with {}: {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(base_url: &str, allowed_paths: &[&str]) -> PakeConfig {
+        PakeConfig {
+            deep_link: DeepLinkConfig {
+                scheme: "myapp".to_string(),
+                base_url: base_url.to_string(),
+                allowed_paths: allowed_paths.iter().map(|path| path.to_string()).collect(),
+            },
+            ..PakeConfig::default()
+        }
+    }
+
+    fn resolve(config: &PakeConfig, link: &str) -> Result<String, String> {
+        resolve_deep_link(config, &Url::parse(link).unwrap()).map(|url| url.to_string())
+    }
+
+    #[test]
+    fn appends_path_query_and_fragment_to_base() {
+        let config = config("https://x.com/app", &[]);
+        assert_eq!(
+            resolve(&config, "myapp://orders/42?tab=log#top").unwrap(),
+            "https://x.com/app/orders/42?tab=log#top"
+        );
+        assert_eq!(resolve(&config, "myapp:///orders/42").unwrap(), "https://x.com/app/orders/42");
+        assert!(resolve(&config, "other://orders/42").is_err());
+    }
+
+    #[test]
+    fn rejects_dot_segments_leaving_the_base() {
+        let config = config("https://x.com/app", &[]);
+        assert!(resolve(&config, "myapp:../other").is_err());
+        assert!(resolve(&config, "myapp:%2e%2e/other").is_err());
+        // `..` inside the link itself is resolved before the base is prepended.
+        assert_eq!(
+            resolve(&config, "myapp://open/../app-admin").unwrap(),
+            "https://x.com/app/open/app-admin"
+        );
+    }
+
+    #[test]
+    fn rejects_sibling_paths_sharing_the_base_prefix() {
+        let config = config("https://x.com/app", &[]);
+        assert!(resolve(&config, "myapp:../app-admin").is_err());
+        assert!(resolve(&config, "myapp:%2e%2e/app-admin").is_err());
+        assert_eq!(resolve(&config, "myapp:").unwrap(), "https://x.com/app/");
+    }
+
+    #[test]
+    fn never_leaves_the_base_origin() {
+        let config = config("https://x.com/app", &[]);
+        for link in [
+            "myapp://evil.com@x.com/a",
+            "myapp:////evil.com/x",
+            "myapp:\\\\evil.com/x",
+            "myapp://evil.com:443/x",
+            "myapp:..%2f..%2f%2fevil.com",
+        ] {
+            if let Ok(target) = resolve(&config, link) {
+                let target = Url::parse(&target).unwrap();
+                assert_eq!(target.host_str(), Some("x.com"), "{}", link);
+                assert!(target.path().starts_with("/app/"), "{}", link);
+            }
+        }
+    }
+
+    #[test]
+    fn applies_allowed_paths_after_normalising() {
+        let config = config("https://x.com", &["/orders/*"]);
+        assert!(resolve(&config, "myapp://orders/42").is_ok());
+        assert!(resolve(&config, "myapp:orders/../admin").is_err());
+        assert!(resolve(&config, "myapp://admin").is_err());
+    }
+}
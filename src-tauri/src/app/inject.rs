@@ -0,0 +1,74 @@
+use crate::app::config::{InjectEntry, InjectKind, InjectRule, PakeConfig};
+use crate::app::invoke::add_log_entry;
+use crate::util::wildcard_to_regex;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+/// Reads the runtime `inject` entries and turns each into an initialization script.
+/// Missing or unreadable files are logged and skipped.
+pub fn load_inject_scripts(app: &AppHandle, config: &PakeConfig) -> Vec<String> {
+    config
+        .inject
+        .iter()
+        .filter_map(|entry| match entry {
+            InjectEntry::Bundled(_) => None,
+            InjectEntry::Runtime(rule) => Some(rule),
+        })
+        .filter_map(|rule| {
+            let Some(path) = resolve_inject_path(app, &rule.path) else {
+                add_log_entry("WARN", &format!("找不到注入文件: {}", rule.path));
+                return None;
+            };
+            match std::fs::read_to_string(&path) {
+                Ok(content) => {
+                    add_log_entry("INFO", &format!("已加载注入文件: {:?}", path));
+                    Some(build_inject_script(rule, &path, &content))
+                }
+                Err(e) => {
+                    add_log_entry("ERROR", &format!("读取注入文件失败: {:?} - {}", path, e));
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+fn resolve_inject_path(app: &AppHandle, path: &str) -> Option<PathBuf> {
+    let path = Path::new(path);
+    if path.is_absolute() {
+        return path.exists().then(|| path.to_path_buf());
+    }
+
+    // The config dir comes first so a file dropped there overrides the bundled one.
+    [app.path().app_config_dir().ok(), app.path().resource_dir().ok()]
+        .into_iter()
+        .flatten()
+        .map(|dir| dir.join(path))
+        .find(|candidate| candidate.exists())
+}
+
+fn build_inject_script(rule: &InjectRule, path: &Path, content: &str) -> String {
+    let kind = rule.kind.unwrap_or_else(|| {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("css") => InjectKind::Css,
+            _ => InjectKind::Js,
+        }
+    });
+
+    let patterns: Vec<String> = rule.matches.iter().map(|p| wildcard_to_regex(p)).collect();
+    let patterns = serde_json::to_string(&patterns).unwrap();
+
+    // Same DOMContentLoaded wrapping as the CLI's combineFiles, guarded by the URL patterns.
+    let body = match kind {
+        InjectKind::Js => content.to_string(),
+        InjectKind::Css => format!(
+            "const style = document.createElement('style'); style.innerHTML = {}; document.head.appendChild(style);",
+            serde_json::to_string(content).unwrap()
+        ),
+    };
+
+    format!(
+        "(function () {{ const patterns = {}; if (patterns.length > 0 && !patterns.some((p) => new RegExp(p).test(window.location.href))) return; window.addEventListener('DOMContentLoaded', (_event) => {{ {}\n }}); }})();",
+        patterns, body
+    )
+}
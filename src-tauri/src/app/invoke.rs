@@ -1,13 +1,21 @@
+use crate::app::log_file::write_log_entry;
+use crate::app::log_level::log_enabled;
+use crate::app::log_stream::dispatch_log_entry;
+use crate::app::otlp::{forward_to_otlp, CommandSpan};
+use crate::app::redact::{redact_recorded, redact_recorded_value};
+use crate::app::syslog::forward_to_syslog;
 use crate::util::{check_file_or_append, get_download_message, show_toast, MessageType};
+use std::cell::Cell;
 use std::fs::{self, File};
 use std::io::Write;
 use std::str::FromStr;
-use std::sync::{Arc, Mutex};
-use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, TryLockError};
+use std::collections::{BTreeMap, VecDeque};
 use tauri::http::Method;
 use tauri::{command, AppHandle, Manager, Url, WebviewWindow};
 use tauri_plugin_http::reqwest::{ClientBuilder, Request};
-use chrono::Local;
+use chrono::{Local, TimeZone};
 use serde::{Deserialize, Serialize};
 
 // 全局日志存储
@@ -16,22 +24,238 @@ static LOG_BUFFER: once_cell::sync::Lazy<Arc<Mutex<VecDeque<LogEntry>>>> =
 
 const MAX_LOG_ENTRIES: usize = 5000;
 
+// 日志序号，清空缓冲区后也不会重置，页面可以据此增量拉取
+static NEXT_LOG_SEQ: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    #[serde(alias = "warning")]
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Trace => "TRACE",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+        }
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(level: &str) -> Result<Self, Self::Err> {
+        match level.to_ascii_uppercase().as_str() {
+            "TRACE" => Ok(LogLevel::Trace),
+            "DEBUG" => Ok(LogLevel::Debug),
+            "INFO" | "LOG" => Ok(LogLevel::Info),
+            "WARN" | "WARNING" => Ok(LogLevel::Warn),
+            "ERROR" => Ok(LogLevel::Error),
+            _ => Err(format!("unknown log level: {}", level)),
+        }
+    }
+}
+
+impl From<log::Level> for LogLevel {
+    fn from(level: log::Level) -> Self {
+        match level {
+            log::Level::Trace => LogLevel::Trace,
+            log::Level::Debug => LogLevel::Debug,
+            log::Level::Info => LogLevel::Info,
+            log::Level::Warn => LogLevel::Warn,
+            log::Level::Error => LogLevel::Error,
+        }
+    }
+}
+
+/// Where an entry was produced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogSource {
+    Rust,
+    /// `console.*` calls captured by the injected script.
+    Webview,
+    /// XHR / fetch requests captured by the injected script.
+    Network,
+}
+
+impl LogSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogSource::Rust => "rust",
+            LogSource::Webview => "webview",
+            LogSource::Network => "network",
+        }
+    }
+}
+
+pub type LogFields = BTreeMap<String, serde_json::Value>;
+
+/// Target of entries added with [`add_log_entry`]; `log::` records keep their module path.
+pub const APP_LOG_TARGET: &str = "pake";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LogEntry {
-    pub timestamp: String,
-    pub level: String,
+    /// Increases by one per entry for the lifetime of the process.
+    pub seq: u64,
+    /// Unix time in milliseconds, same as `Date.now()` in the page.
+    pub timestamp: i64,
+    pub level: LogLevel,
+    pub source: LogSource,
+    pub target: String,
     pub message: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: LogFields,
+}
+
+impl LogEntry {
+    pub fn formatted_time(&self) -> String {
+        Local
+            .timestamp_millis_opt(self.timestamp)
+            .single()
+            .map(|time| time.format("%Y-%m-%d %H:%M:%S%.3f").to_string())
+            .unwrap_or_default()
+    }
+
+    /// `2024-05-01 12:00:00.000 [INFO] pake: message key=value`
+    pub fn to_line(&self) -> String {
+        let mut line = format!(
+            "{} [{}] {}: {}",
+            self.formatted_time(),
+            self.level.as_str(),
+            self.target,
+            self.message
+        );
+        for (key, value) in &self.fields {
+            // 字符串不带引号输出
+            match value.as_str() {
+                Some(value) => line.push_str(&format!(" {}={}", key, value)),
+                None => line.push_str(&format!(" {}={}", key, value)),
+            }
+        }
+        line
+    }
 }
 
 // 添加日志条目的函数
 pub fn add_log_entry(level: &str, message: &str) {
+    let level = LogLevel::from_str(level).unwrap_or(LogLevel::Info);
+    if !log_enabled(level, LogSource::Rust, APP_LOG_TARGET) {
+        return;
+    }
+    push_log_entry(level, LogSource::Rust, APP_LOG_TARGET, message.to_string(), LogFields::new());
+}
+
+thread_local! {
+    // 当前线程正持有日志相关的锁
+    static RECORDING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Marks this thread as recording until dropped, so a panic part-way through still clears it.
+struct RecordingGuard;
+
+impl RecordingGuard {
+    fn enter() -> Self {
+        RECORDING.with(|recording| recording.set(true));
+        RecordingGuard
+    }
+}
+
+impl Drop for RecordingGuard {
+    fn drop(&mut self) {
+        RECORDING.with(|recording| recording.set(false));
+    }
+}
+
+/// Appends to the buffer and hands the entry to every sink without checking the level.
+///
+/// The sinks (`redact_recorded`, `write_log_entry`, `dispatch_log_entry`, `forward_to_syslog`
+/// and `forward_to_otlp`) run on the caller's thread, most of them under the buffer lock, so
+/// they must never block or log themselves. A sink started later replays the buffer and
+/// registers inside [`with_log_buffer`], which hands it every entry exactly once.
+pub(crate) fn push_log_entry(
+    level: LogLevel,
+    source: LogSource,
+    target: &str,
+    message: String,
+    fields: LogFields,
+) {
+    let _recording = RecordingGuard::enter();
+    let (message, fields) = redact_entry(message, fields);
+    append_log_entry(&mut lock_log_buffer(), level, source, target, message, fields);
+}
+
+/// [`push_log_entry`] for the panic hook: gives up instead of waiting when this thread is
+/// already recording or another one holds the buffer, since that path may be the one that
+/// panicked. Returns whether the entry was recorded.
+pub(crate) fn try_push_log_entry(
+    level: LogLevel,
+    source: LogSource,
+    target: &str,
+    message: String,
+    fields: LogFields,
+) -> bool {
+    if RECORDING.with(|recording| recording.get()) {
+        return false;
+    }
+    let _recording = RecordingGuard::enter();
+    let (message, fields) = redact_entry(message, fields);
+    // 被其他线程持有时跳过，不在 panic hook 里等待
+    let mut buffer = match LOG_BUFFER.try_lock() {
+        Ok(buffer) => buffer,
+        Err(TryLockError::Poisoned(e)) => e.into_inner(),
+        Err(TryLockError::WouldBlock) => return false,
+    };
+    append_log_entry(&mut buffer, level, source, target, message, fields);
+    true
+}
+
+// 脱敏在加锁之前完成，缓冲区、日志文件和推送都只会看到脱敏后的内容
+fn redact_entry(message: String, fields: LogFields) -> (String, LogFields) {
+    let message = redact_recorded(message);
+    let fields = fields
+        .into_iter()
+        .map(|(key, value)| (key, redact_recorded_value(value)))
+        .collect();
+    (message, fields)
+}
+
+fn append_log_entry(
+    buffer: &mut VecDeque<LogEntry>,
+    level: LogLevel,
+    source: LogSource,
+    target: &str,
+    message: String,
+    fields: LogFields,
+) {
+    // 在锁内分配序号，保证缓冲区中的序号严格递增
     let entry = LogEntry {
-        timestamp: Local::now().format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
-        level: level.to_string(),
-        message: message.to_string(),
+        seq: NEXT_LOG_SEQ.fetch_add(1, Ordering::Relaxed),
+        timestamp: Local::now().timestamp_millis(),
+        level,
+        source,
+        target: target.to_string(),
+        message,
+        fields,
     };
 
-    let mut buffer = LOG_BUFFER.lock().unwrap();
+    #[cfg(debug_assertions)]
+    eprintln!("{}", entry.to_line());
+
+    // 持有缓冲区锁时交给各个输出，保证它们看到的顺序与缓冲区一致
+    write_log_entry(&entry);
+    dispatch_log_entry(&entry);
+    forward_to_syslog(&entry);
+    forward_to_otlp(&entry);
     buffer.push_back(entry);
 
     // 保持最大条目数限制
@@ -40,6 +264,104 @@ pub fn add_log_entry(level: &str, message: &str) {
     }
 }
 
+/// A sink that panicked under the lock poisons it; the buffer itself stays usable, so
+/// logging carries on instead of panicking on every later entry.
+fn lock_log_buffer() -> MutexGuard<'static, VecDeque<LogEntry>> {
+    LOG_BUFFER.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+pub(crate) fn with_log_buffer<R>(f: impl FnOnce(&VecDeque<LogEntry>) -> R) -> R {
+    f(&lock_log_buffer())
+}
+
+/// Filters for `get_logs`; every field is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LogQuery {
+    pub min_level: Option<LogLevel>,
+    /// Only entries with a larger `seq`, for incremental polling.
+    pub since_seq: Option<u64>,
+    /// Unix milliseconds, inclusive.
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    /// Empty matches every source.
+    pub sources: Vec<LogSource>,
+    /// Case-insensitive substring of the message or target.
+    pub contains: Option<String>,
+    /// Regular expression matched against the message.
+    pub regex: Option<String>,
+    pub offset: usize,
+    pub limit: Option<usize>,
+    /// Page from the newest entry backwards; entries are still returned oldest first.
+    pub newest_first: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogPage {
+    pub entries: Vec<LogEntry>,
+    /// Number of entries matching the filters, before `offset` and `limit`.
+    pub total: usize,
+    /// Newest `seq` in the buffer, to be passed back as `since_seq`.
+    pub last_seq: u64,
+}
+
+impl LogQuery {
+    pub fn run(&self, entries: &VecDeque<LogEntry>) -> Result<LogPage, String> {
+        let regex = self
+            .regex
+            .as_deref()
+            .filter(|pattern| !pattern.is_empty())
+            .map(regex::Regex::new)
+            .transpose()
+            .map_err(|e| format!("无效的正则表达式: {}", e))?;
+        let contains = self
+            .contains
+            .as_deref()
+            .filter(|text| !text.is_empty())
+            .map(str::to_lowercase);
+
+        let matching: Vec<&LogEntry> = entries
+            .iter()
+            .filter(|entry| self.min_level.map_or(true, |level| entry.level >= level))
+            .filter(|entry| self.since_seq.map_or(true, |seq| entry.seq > seq))
+            .filter(|entry| self.from.map_or(true, |from| entry.timestamp >= from))
+            .filter(|entry| self.to.map_or(true, |to| entry.timestamp <= to))
+            .filter(|entry| self.sources.is_empty() || self.sources.contains(&entry.source))
+            .filter(|entry| {
+                contains.as_ref().map_or(true, |text| {
+                    entry.message.to_lowercase().contains(text)
+                        || entry.target.to_lowercase().contains(text)
+                })
+            })
+            .filter(|entry| regex.as_ref().map_or(true, |regex| regex.is_match(&entry.message)))
+            .collect();
+
+        let total = matching.len();
+        let limit = self.limit.unwrap_or(usize::MAX);
+        let page: Vec<LogEntry> = if self.newest_first {
+            matching.iter().rev().skip(self.offset).take(limit).rev().map(|e| (*e).clone()).collect()
+        } else {
+            matching.iter().skip(self.offset).take(limit).map(|e| (*e).clone()).collect()
+        };
+
+        Ok(LogPage {
+            entries: page,
+            total,
+            last_seq: entries.back().map_or(0, |entry| entry.seq),
+        })
+    }
+}
+
+/// Entry sent by the injected script for `push_logs`.
+#[derive(Debug, Deserialize)]
+pub struct WebLogEntry {
+    pub level: LogLevel,
+    pub source: LogSource,
+    pub message: String,
+    #[serde(default)]
+    pub fields: LogFields,
+}
+
 #[derive(serde::Deserialize)]
 pub struct DownloadFileParams {
     url: String,
@@ -56,15 +378,36 @@ pub struct BinaryDownloadParams {
 pub struct NotificationParams {
     title: String,
     body: String,
+    #[serde(default)]
     icon: String,
 }
 
+impl NotificationParams {
+    pub fn new(title: &str, body: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            body: body.to_string(),
+            icon: String::new(),
+        }
+    }
+}
+
 #[command]
-pub async fn download_file(app: AppHandle, params: DownloadFileParams) -> Result<(), String> {
+pub async fn download_file(
+    app: AppHandle,
+    window: WebviewWindow,
+    params: DownloadFileParams,
+) -> Result<(), String> {
+    let span = CommandSpan::start("download_file")
+        .attribute("pake.window", window.label())
+        .attribute("pake.download.filename", params.filename.as_str());
+    span.finish(download_url(app, window, params).await)
+}
+
+async fn download_url(app: AppHandle, window: WebviewWindow, params: DownloadFileParams) -> Result<(), String> {
     let filename = params.filename.clone();
     let url = params.url.clone();
     add_log_entry("INFO", &format!("开始下载文件: {}", filename));
-    let window: WebviewWindow = app.get_webview_window("pake").unwrap();
     show_toast(&window, &get_download_message(MessageType::Start));
 
     let output_path = app.path().download_dir().unwrap().join(params.filename);
@@ -99,11 +442,11 @@ pub async fn download_file(app: AppHandle, params: DownloadFileParams) -> Result
 #[command]
 pub async fn download_file_by_binary(
     app: AppHandle,
+    window: WebviewWindow,
     params: BinaryDownloadParams,
 ) -> Result<(), String> {
     let filename = params.filename.clone();
     add_log_entry("INFO", &format!("开始二进制文件下载: {}", filename));
-    let window: WebviewWindow = app.get_webview_window("pake").unwrap();
     show_toast(&window, &get_download_message(MessageType::Start));
     let output_path = app.path().download_dir().unwrap().join(params.filename);
     let file_path = check_file_or_append(output_path.to_str().unwrap());
@@ -123,7 +466,16 @@ pub async fn download_file_by_binary(
 }
 
 #[command]
-pub fn send_notification(app: AppHandle, params: NotificationParams) -> Result<(), String> {
+pub fn send_notification(
+    app: AppHandle,
+    window: WebviewWindow,
+    params: NotificationParams,
+) -> Result<(), String> {
+    let span = CommandSpan::start("send_notification").attribute("pake.window", window.label());
+    span.finish(notify(app, window, params))
+}
+
+fn notify(app: AppHandle, window: WebviewWindow, params: NotificationParams) -> Result<(), String> {
     let title = params.title.clone();
     add_log_entry("INFO", &format!("发送通知: {}", title));
     use tauri_plugin_notification::NotificationExt;
@@ -132,8 +484,10 @@ pub fn send_notification(app: AppHandle, params: NotificationParams) -> Result<(
     let mut notification_builder = app.notification()
         .builder()
         .title(&params.title)
-        .body(&params.body)
-        .icon(&params.icon);
+        .body(&params.body);
+    if !params.icon.is_empty() {
+        notification_builder = notification_builder.icon(&params.icon);
+    }
 
     // 根据平台设置通知声音
     #[cfg(target_os = "macos")]
@@ -152,42 +506,256 @@ pub fn send_notification(app: AppHandle, params: NotificationParams) -> Result<(
     }
 
     notification_builder.show().unwrap();
-    add_log_entry("INFO", "通知发送成功");
-
-    // 请求用户注意力，让系统托盘图标跳动
-    if let Some(window) = app.get_webview_window("pake") {
-        #[cfg(target_os = "macos")]
-        {
-            use tauri::UserAttentionType;
-            let _ = window.request_user_attention(Some(UserAttentionType::Informational));
-        }
+    add_log_entry("DEBUG", "通知发送成功");
 
-        #[cfg(target_os = "windows")]
-        {
-            use tauri::UserAttentionType;
-            let _ = window.request_user_attention(Some(UserAttentionType::Informational));
-        }
-
-        #[cfg(target_os = "linux")]
-        {
-            use tauri::UserAttentionType;
-            let _ = window.request_user_attention(Some(UserAttentionType::Informational));
-        }
+    // 请求用户注意力，让发出通知的窗口对应的图标跳动
+    {
+        use tauri::UserAttentionType;
+        let _ = window.request_user_attention(Some(UserAttentionType::Informational));
     }
 
     Ok(())
 }
 
 #[command]
-pub fn get_logs() -> Result<Vec<LogEntry>, String> {
-    let buffer = LOG_BUFFER.lock().unwrap();
-    Ok(buffer.iter().cloned().collect())
+pub fn get_logs(query: Option<LogQuery>) -> Result<LogPage, String> {
+    query.unwrap_or_default().run(&lock_log_buffer())
+}
+
+/// Console and network entries from the page, so they share the buffer, files and uploads.
+#[command]
+pub fn push_logs(window: WebviewWindow, entries: Vec<WebLogEntry>) {
+    for entry in entries {
+        // 页面不能伪造 Rust 端的日志
+        let source = match entry.source {
+            LogSource::Rust => LogSource::Webview,
+            source => source,
+        };
+        if !log_enabled(entry.level, source, window.label()) {
+            continue;
+        }
+        push_log_entry(entry.level, source, window.label(), entry.message, entry.fields);
+    }
 }
 
 #[command]
 pub fn clear_logs() -> Result<(), String> {
-    let mut buffer = LOG_BUFFER.lock().unwrap();
-    buffer.clear();
+    // 先释放缓冲区锁，add_log_entry 还要再获取它
+    lock_log_buffer().clear();
     add_log_entry("INFO", "日志已清空");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn try_push(message: &str) -> bool {
+        try_push_log_entry(LogLevel::Error, LogSource::Rust, "test", message.to_string(), LogFields::new())
+    }
+
+    fn buffered(message: &str) -> bool {
+        with_log_buffer(|buffer| buffer.iter().any(|entry| entry.message == message))
+    }
+
+    // 两种情况放在同一个测试里，避免并行的测试争用全局缓冲区
+    #[test]
+    fn try_push_skips_while_buffer_is_busy() {
+        let buffer = lock_log_buffer();
+        let recorded = std::thread::spawn(|| try_push("skipped while held")).join().unwrap();
+        drop(buffer);
+        assert!(!recorded);
+        assert!(!buffered("skipped while held"));
+
+        // 其他测试的后台线程也可能短暂持有缓冲区，稍等重试
+        let recorded = (0..100).any(|_| {
+            try_push("recorded once free") || {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+                false
+            }
+        });
+        assert!(recorded);
+        assert!(buffered("recorded once free"));
+
+        // 同一线程正在记录时不能再次加锁
+        let recording = RecordingGuard::enter();
+        assert!(!try_push("skipped while recording"));
+        drop(recording);
+        assert!(!buffered("skipped while recording"));
+    }
+
+    #[test]
+    fn logging_survives_a_panic_under_the_buffer_lock() {
+        let result = std::thread::spawn(|| {
+            let _buffer = lock_log_buffer();
+            panic!("sink panicked");
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(LOG_BUFFER.is_poisoned());
+
+        push_log_entry(LogLevel::Info, LogSource::Rust, "test", "after poison".to_string(), LogFields::new());
+        assert!(buffered("after poison"));
+        assert!(get_logs(None).is_ok());
+        let recorded = (0..100).any(|_| {
+            try_push("try after poison") || {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+                false
+            }
+        });
+        assert!(recorded);
+        assert!(buffered("try after poison"));
+    }
+
+    #[test]
+    fn recording_flag_is_cleared_by_unwinding() {
+        let result = std::panic::catch_unwind(|| {
+            let _recording = RecordingGuard::enter();
+            panic!("inside a sink");
+        });
+        assert!(result.is_err());
+        assert!(!RECORDING.with(|recording| recording.get()));
+    }
+
+    fn entries() -> VecDeque<LogEntry> {
+        [
+            (LogLevel::Debug, LogSource::Rust, "pake", "starting up"),
+            (LogLevel::Info, LogSource::Webview, "main", "Page loaded"),
+            (LogLevel::Warn, LogSource::Network, "main", "GET /api/orders 500"),
+            (LogLevel::Error, LogSource::Rust, "app_lib::app::log_upload", "upload failed"),
+            (LogLevel::Info, LogSource::Rust, "reqwest", "connection reused"),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(index, (level, source, target, message))| LogEntry {
+            seq: index as u64 + 1,
+            timestamp: 1_000 * (index as i64 + 1),
+            level,
+            source,
+            target: target.to_string(),
+            message: message.to_string(),
+            fields: LogFields::new(),
+        })
+        .collect()
+    }
+
+    fn seqs(page: &LogPage) -> Vec<u64> {
+        page.entries.iter().map(|entry| entry.seq).collect()
+    }
+
+    #[test]
+    fn query_filters_entries() {
+        let cases: Vec<(&str, LogQuery, Vec<u64>)> = vec![
+            ("no filters", LogQuery::default(), vec![1, 2, 3, 4, 5]),
+            (
+                "min level",
+                LogQuery { min_level: Some(LogLevel::Warn), ..Default::default() },
+                vec![3, 4],
+            ),
+            (
+                "sources",
+                LogQuery { sources: vec![LogSource::Webview, LogSource::Network], ..Default::default() },
+                vec![2, 3],
+            ),
+            (
+                "text matches message case-insensitively",
+                LogQuery { contains: Some("PAGE".to_string()), ..Default::default() },
+                vec![2],
+            ),
+            (
+                "text matches target",
+                LogQuery { contains: Some("log_upload".to_string()), ..Default::default() },
+                vec![4],
+            ),
+            (
+                "empty text matches everything",
+                LogQuery { contains: Some(String::new()), ..Default::default() },
+                vec![1, 2, 3, 4, 5],
+            ),
+            (
+                "regex on message",
+                LogQuery { regex: Some(r"^\w+ /api/.* 5\d\d$".to_string()), ..Default::default() },
+                vec![3],
+            ),
+            ("since seq", LogQuery { since_seq: Some(3), ..Default::default() }, vec![4, 5]),
+            (
+                "time range is inclusive",
+                LogQuery { from: Some(2_000), to: Some(4_000), ..Default::default() },
+                vec![2, 3, 4],
+            ),
+            (
+                "filters combine",
+                LogQuery {
+                    min_level: Some(LogLevel::Info),
+                    sources: vec![LogSource::Rust],
+                    since_seq: Some(1),
+                    ..Default::default()
+                },
+                vec![4, 5],
+            ),
+        ];
+        let entries = entries();
+        for (name, query, expected) in cases {
+            let page = query.run(&entries).unwrap();
+            assert_eq!(seqs(&page), expected, "{}", name);
+            assert_eq!(page.total, expected.len(), "{}", name);
+            assert_eq!(page.last_seq, 5, "{}", name);
+        }
+    }
+
+    #[test]
+    fn query_pages_from_either_end() {
+        let cases: Vec<(usize, Option<usize>, bool, Vec<u64>)> = vec![
+            (0, Some(2), false, vec![1, 2]),
+            (2, Some(2), false, vec![3, 4]),
+            (4, Some(2), false, vec![5]),
+            (5, Some(2), false, vec![]),
+            (0, Some(2), true, vec![4, 5]),
+            (2, Some(2), true, vec![2, 3]),
+            (4, Some(2), true, vec![1]),
+            (1, None, true, vec![1, 2, 3, 4]),
+            (0, Some(0), false, vec![]),
+        ];
+        let entries = entries();
+        for (offset, limit, newest_first, expected) in cases {
+            let query = LogQuery { offset, limit, newest_first, ..Default::default() };
+            let page = query.run(&entries).unwrap();
+            assert_eq!(seqs(&page), expected, "offset {} limit {:?} newest_first {}", offset, limit, newest_first);
+            assert_eq!(page.total, 5);
+        }
+    }
+
+    #[test]
+    fn query_polls_with_last_seq() {
+        let mut entries = entries();
+        let first = LogQuery::default().run(&entries).unwrap();
+        assert!(LogQuery { since_seq: Some(first.last_seq), ..Default::default() }
+            .run(&entries)
+            .unwrap()
+            .entries
+            .is_empty());
+
+        let mut next = entries.back().unwrap().clone();
+        next.seq = 6;
+        entries.push_back(next);
+        let page = LogQuery { since_seq: Some(first.last_seq), ..Default::default() }
+            .run(&entries)
+            .unwrap();
+        assert_eq!(seqs(&page), [6]);
+        assert_eq!(page.last_seq, 6);
+
+        let empty = LogQuery::default().run(&VecDeque::new()).unwrap();
+        assert_eq!((empty.total, empty.last_seq), (0, 0));
+    }
+
+    #[test]
+    fn query_rejects_invalid_regex() {
+        let query = LogQuery { regex: Some("(unclosed".to_string()), ..Default::default() };
+        let error = query.run(&entries()).unwrap_err();
+        assert!(error.starts_with("无效的正则表达式"), "{}", error);
+
+        // 空的正则等同于不过滤
+        let query = LogQuery { regex: Some(String::new()), ..Default::default() };
+        assert_eq!(query.run(&entries()).unwrap().total, 5);
+    }
+}
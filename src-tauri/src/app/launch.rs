@@ -0,0 +1,158 @@
+use crate::app::args::AppArgs;
+use crate::app::config::PakeConfig;
+use crate::app::deep_link::resolve_deep_link;
+use crate::app::invoke::add_log_entry;
+use crate::util::get_main_window;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{command, AppHandle, Emitter, Manager, State, Url};
+
+/// Emitted to the main window when files are passed on the command line.
+pub const OPEN_FILES_EVENT: &str = "pake://open-files";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenedFile {
+    pub path: String,
+    pub name: String,
+}
+
+/// Files waiting for the page. They are also sent with [`OPEN_FILES_EVENT`], but a page that
+/// was still loading (e.g. at cold start) can collect them with `take_opened_files`.
+#[derive(Default)]
+pub struct PendingFiles(pub Mutex<Vec<OpenedFile>>);
+
+pub enum LaunchTarget {
+    Url(Url),
+    File(PathBuf),
+}
+
+/// Existing paths (relative to `cwd`) are files, everything else has to parse as a URL.
+pub fn classify_targets(targets: &[String], cwd: &Path) -> Vec<LaunchTarget> {
+    targets
+        .iter()
+        .filter_map(|target| {
+            let path = cwd.join(target);
+            if path.exists() {
+                return Some(LaunchTarget::File(path));
+            }
+            match Url::parse(target) {
+                Ok(url) => Some(LaunchTarget::Url(url)),
+                Err(e) => {
+                    add_log_entry("WARN", &format!("无法识别的启动参数: {} - {}", target, e));
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Callback of the single-instance plugin: brings the running window to the front and
+/// hands it whatever the new launch was asked to open.
+pub fn handle_second_instance(app: &AppHandle, argv: Vec<String>, cwd: String) {
+    add_log_entry("INFO", &format!("收到新实例的启动参数: {:?}, 工作目录: {}", argv, cwd));
+
+    let args = AppArgs::parse(argv.into_iter().skip(1)).unwrap_or_else(|e| {
+        add_log_entry("WARN", &format!("无法解析新实例的启动参数: {}", e));
+        AppArgs::default()
+    });
+
+    let Some(window) = get_main_window(app) else {
+        add_log_entry("ERROR", "没有可用的窗口来处理新实例的启动参数");
+        return;
+    };
+    let _ = window.unminimize();
+    let _ = window.show();
+    let _ = window.set_focus();
+
+    let mut targets: Vec<String> = args.url.into_iter().collect();
+    targets.extend(args.targets);
+    open_targets(app, &targets, Path::new(&cwd));
+}
+
+/// Navigates the main window to the last allowed URL and passes files to the page.
+/// Links using the configured `deep_link.scheme` are mapped onto the base URL first.
+pub fn open_targets(app: &AppHandle, targets: &[String], cwd: &Path) {
+    if targets.is_empty() {
+        return;
+    }
+
+    let mut urls = Vec::new();
+    let mut files = Vec::new();
+    for target in classify_targets(targets, cwd) {
+        match target {
+            LaunchTarget::Url(url) => urls.push(url),
+            LaunchTarget::File(path) => files.push(OpenedFile {
+                name: path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                path: path.to_string_lossy().into_owned(),
+            }),
+        }
+    }
+
+    if let Some(url) = urls.pop() {
+        match app.try_state::<PakeConfig>() {
+            Some(config) if url.scheme().eq_ignore_ascii_case(&config.deep_link.scheme) => {
+                match resolve_deep_link(&config, &url) {
+                    // Already confined to the configured base URL.
+                    Ok(target) => navigate_to(app, target),
+                    Err(e) => add_log_entry("WARN", &format!("拒绝打开链接: {} - {}", url, e)),
+                }
+            }
+            _ => navigate_main_window(app, url),
+        }
+    }
+
+    if !files.is_empty() {
+        add_log_entry("INFO", &format!("传递 {} 个文件给页面", files.len()));
+        if let Some(pending) = app.try_state::<PendingFiles>() {
+            pending.0.lock().unwrap().extend(files.iter().cloned());
+        }
+        if let Some(window) = get_main_window(app) {
+            if let Err(e) = window.emit(OPEN_FILES_EVENT, &files) {
+                add_log_entry("ERROR", &format!("发送文件打开事件失败: {}", e));
+            }
+        }
+    }
+}
+
+/// Only origins of the configured windows and profiles may be opened in the app.
+pub fn navigate_main_window(app: &AppHandle, url: Url) {
+    let allowed = app
+        .try_state::<PakeConfig>()
+        .map(|config| is_allowed_origin(&config, &url))
+        .unwrap_or(false);
+    if !allowed {
+        add_log_entry("WARN", &format!("拒绝打开未配置来源的地址: {}", url));
+        return;
+    }
+    navigate_to(app, url);
+}
+
+fn navigate_to(app: &AppHandle, url: Url) {
+    match get_main_window(app) {
+        Some(mut window) => match window.navigate(url.clone()) {
+            Ok(_) => add_log_entry("INFO", &format!("已跳转到: {}", url)),
+            Err(e) => add_log_entry("ERROR", &format!("跳转失败: {} - {}", url, e)),
+        },
+        None => add_log_entry("ERROR", &format!("没有可用的窗口来打开: {}", url)),
+    }
+}
+
+fn is_allowed_origin(config: &PakeConfig, url: &Url) -> bool {
+    config
+        .windows
+        .iter()
+        .filter(|window| window.url_type == "web")
+        .map(|window| window.url.as_str())
+        .chain(config.profiles.iter().map(|profile| profile.url.as_str()))
+        .filter_map(|configured| Url::parse(configured).ok())
+        .any(|configured| configured.origin() == url.origin())
+}
+
+#[command]
+pub fn take_opened_files(pending: State<PendingFiles>) -> Vec<OpenedFile> {
+    std::mem::take(&mut *pending.0.lock().unwrap())
+}
@@ -0,0 +1,87 @@
+use crate::app::config::PakeConfig;
+use crate::app::invoke::{add_log_entry, send_notification, NotificationParams};
+use crate::app::log_upload::{BundleJob, LogsPayload};
+use crate::app::report::FeedbackReport;
+use crate::util::get_main_window;
+use std::path::PathBuf;
+use tauri::{command, AppHandle, Manager, State, WebviewWindow};
+use tauri_plugin_dialog::DialogExt;
+use tokio::sync::oneshot;
+
+/// Asks where to save `file_name`, starting in the downloads folder. `None` when cancelled.
+async fn ask_save_path(app: &AppHandle, file_name: &str) -> Result<Option<PathBuf>, String> {
+    let extension = if file_name.ends_with(".age") { "age" } else { "zip" };
+    let mut dialog = app
+        .dialog()
+        .file()
+        .set_title("导出诊断信息")
+        .set_file_name(file_name)
+        .add_filter("诊断信息", &[extension]);
+    if let Ok(dir) = app.path().download_dir() {
+        dialog = dialog.set_directory(dir);
+    }
+
+    let (sender, receiver) = oneshot::channel();
+    dialog.save_file(move |path| {
+        let _ = sender.send(path);
+    });
+    match receiver.await.map_err(|e| e.to_string())? {
+        Some(path) => path.into_path().map(Some).map_err(|e| e.to_string()),
+        None => Ok(None),
+    }
+}
+
+/// Builds the same bundle as `handle_log_upload` and saves it where the user picks.
+pub(crate) async fn export_bundle(app: &AppHandle, job: BundleJob) -> Result<Option<PathBuf>, String> {
+    let Some(path) = ask_save_path(app, &job.file_name).await? else {
+        return Ok(None);
+    };
+    job.write(&path).await?;
+    add_log_entry("INFO", &format!("诊断信息已导出: {}", path.display()));
+    Ok(Some(path))
+}
+
+/// Tauri Command: 将日志导出到本地文件，供无法访问上传地址的环境使用。
+/// `logs` 省略时使用 Rust 端的日志缓冲区。返回保存的路径，用户取消时返回 `None`。
+#[command]
+pub async fn export_logs(
+    app: AppHandle,
+    window: WebviewWindow,
+    config: State<'_, PakeConfig>,
+    logs: Option<LogsPayload>,
+    report: Option<FeedbackReport>,
+) -> Result<Option<String>, String> {
+    let report = report
+        .map(|report| report.validate(window.url().ok().map(|url| url.to_string())))
+        .transpose()?;
+    let logs = logs.unwrap_or_else(LogsPayload::from_log_buffer);
+    let job = BundleJob::prepare(&app, &config, logs, report)?;
+    export_bundle(&app, job)
+        .await
+        .map(|path| path.map(|path| path.to_string_lossy().into_owned()))
+        .inspect_err(|e| add_log_entry("ERROR", &format!("导出诊断信息失败: {}", e)))
+}
+
+/// Tray entry: exports the Rust-side log buffer and reports the outcome as a notification,
+/// since there may be no page able to show it.
+pub fn export_diagnostics(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let config = app.state::<PakeConfig>();
+        let result = match BundleJob::prepare(&app, &config, LogsPayload::from_log_buffer(), None) {
+            Ok(job) => export_bundle(&app, job).await,
+            Err(e) => Err(e),
+        };
+        let (title, body) = match result {
+            Ok(None) => return,
+            Ok(Some(path)) => ("诊断信息已导出".to_string(), path.display().to_string()),
+            Err(e) => {
+                add_log_entry("ERROR", &format!("导出诊断信息失败: {}", e));
+                ("导出诊断信息失败".to_string(), e)
+            }
+        };
+        if let Some(window) = get_main_window(&app) {
+            let _ = send_notification(app.clone(), window, NotificationParams::new(&title, &body));
+        }
+    });
+}
@@ -0,0 +1,396 @@
+use crate::app::config::LogFileConfig;
+use crate::app::invoke::{with_log_buffer, LogEntry};
+use chrono::{DateTime, Local, NaiveDate};
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, sync_channel, Receiver, Sender, SyncSender};
+use std::time::{Duration, SystemTime};
+use tauri::{command, AppHandle, Manager};
+
+// 写文件线程的队列，未初始化或已禁用时为空
+static LOG_WRITER: once_cell::sync::OnceCell<Sender<FileCommand>> =
+    once_cell::sync::OnceCell::new();
+
+const LOG_FILE_PREFIX: &str = "pake-";
+const LOG_FILE_SUFFIX: &str = ".log";
+const WRITER_THREAD: &str = "pake-log-file";
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(2);
+
+enum FileCommand {
+    Write(LogEntry),
+    /// Answered once every earlier entry has been written.
+    Flush(SyncSender<()>),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogFileInfo {
+    pub name: String,
+    pub size: u64,
+    pub modified: String,
+}
+
+/// `pake-2024-05-01.log`, then `pake-2024-05-01.1.log`, `pake-2024-05-01.2.log`, ...
+/// once the size limit is reached on the same day.
+struct RotatingLogFile {
+    dir: PathBuf,
+    config: LogFileConfig,
+    date: NaiveDate,
+    index: u32,
+    file: File,
+    size: u64,
+}
+
+impl RotatingLogFile {
+    fn open(dir: PathBuf, config: LogFileConfig) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let date = Local::now().date_naive();
+        let index = latest_index(&dir, date);
+        let (file, size) = open_append(&dir.join(file_name(date, index)))?;
+        prune_log_files(&dir, &config);
+        Ok(Self {
+            dir,
+            config,
+            date,
+            index,
+            file,
+            size,
+        })
+    }
+
+    fn write_entry(&mut self, entry: &LogEntry) -> io::Result<()> {
+        let line = format!("{}\n", entry.to_line());
+
+        let today = Local::now().date_naive();
+        if today != self.date {
+            self.rotate(today, latest_index(&self.dir, today))?;
+        } else if self.size > 0 && self.size + line.len() as u64 > self.config.max_file_size {
+            self.rotate(today, self.index + 1)?;
+        }
+
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self, date: NaiveDate, index: u32) -> io::Result<()> {
+        let (file, size) = open_append(&self.dir.join(file_name(date, index)))?;
+        self.file = file;
+        self.size = size;
+        self.date = date;
+        self.index = index;
+        prune_log_files(&self.dir, &self.config);
+        Ok(())
+    }
+}
+
+/// Starts the writer thread for `dir`, beginning with the entries already in the buffer.
+pub fn init_log_file(dir: PathBuf, config: &LogFileConfig) -> io::Result<()> {
+    if !config.enabled || LOG_WRITER.get().is_some() {
+        return Ok(());
+    }
+
+    let log_file = RotatingLogFile::open(dir, config.clone())?;
+    let (sender, receiver) = channel();
+    std::thread::Builder::new()
+        .name(WRITER_THREAD.to_string())
+        .spawn(move || run(log_file, receiver))?;
+
+    // 补发并注册，见 push_log_entry
+    with_log_buffer(|entries| {
+        for entry in entries {
+            let _ = sender.send(FileCommand::Write(entry.clone()));
+        }
+        let _ = LOG_WRITER.set(sender);
+    });
+    Ok(())
+}
+
+fn run(mut log_file: RotatingLogFile, receiver: Receiver<FileCommand>) {
+    for command in receiver {
+        match command {
+            FileCommand::Write(entry) => {
+                // 不能再调用 add_log_entry，否则日志会写回自己的队列
+                if let Err(e) = log_file.write_entry(&entry) {
+                    eprintln!("写入日志文件失败: {}", e);
+                }
+            }
+            FileCommand::Flush(done) => {
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+/// Queues `entry` for the writer thread. A `push_log_entry` sink, see the rules there. The
+/// queue is unbounded so the file never misses an entry, and rotation and pruning stay off
+/// the buffer lock.
+pub fn write_log_entry(entry: &LogEntry) {
+    if let Some(sender) = LOG_WRITER.get() {
+        let _ = sender.send(FileCommand::Write(entry.clone()));
+    }
+}
+
+/// Waits (bounded) until everything queued so far is on disk, e.g. before the process
+/// exits or while a panic is being reported.
+pub fn flush_log_file() {
+    let Some(sender) = LOG_WRITER.get() else {
+        return;
+    };
+    // 写文件线程自己崩溃时没有人会应答
+    if std::thread::current().name() == Some(WRITER_THREAD) {
+        return;
+    }
+    let (done, wait) = sync_channel(1);
+    if sender.send(FileCommand::Flush(done)).is_ok() {
+        let _ = wait.recv_timeout(FLUSH_TIMEOUT);
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<(File, u64)> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let size = file.metadata()?.len();
+    Ok((file, size))
+}
+
+fn file_name(date: NaiveDate, index: u32) -> String {
+    if index == 0 {
+        format!("{}{}{}", LOG_FILE_PREFIX, date.format("%Y-%m-%d"), LOG_FILE_SUFFIX)
+    } else {
+        format!("{}{}.{}{}", LOG_FILE_PREFIX, date.format("%Y-%m-%d"), index, LOG_FILE_SUFFIX)
+    }
+}
+
+/// Inverse of [`file_name`]; anything else in the log dir is not ours.
+fn parse_file_name(name: &str) -> Option<(NaiveDate, u32)> {
+    let stem = name.strip_prefix(LOG_FILE_PREFIX)?.strip_suffix(LOG_FILE_SUFFIX)?;
+    let (date, index) = match stem.split_once('.') {
+        Some((date, index)) => (date, index.parse().ok()?),
+        None => (stem, 0),
+    };
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    Some((date, index))
+}
+
+fn latest_index(dir: &Path, date: NaiveDate) -> u32 {
+    log_files(dir)
+        .into_iter()
+        .filter(|(_, file_date, _)| *file_date == date)
+        .map(|(_, _, index)| index)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Log files in `dir`, newest first.
+fn log_files(dir: &Path) -> Vec<(PathBuf, NaiveDate, u32)> {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<_> = read_dir
+        .flatten()
+        .filter_map(|entry| {
+            let (date, index) = parse_file_name(entry.file_name().to_str()?)?;
+            Some((entry.path(), date, index))
+        })
+        .collect();
+    files.sort_by_key(|(_, date, index)| std::cmp::Reverse((*date, *index)));
+    files
+}
+
+/// Keeps at most `max_files` files and drops those older than `retention_days`.
+fn prune_log_files(dir: &Path, config: &LogFileConfig) {
+    let cutoff = (config.retention_days > 0).then(|| {
+        SystemTime::now() - Duration::from_secs(u64::from(config.retention_days) * 24 * 60 * 60)
+    });
+
+    // The newest file is the one being written, never remove it.
+    for (position, (path, _, _)) in log_files(dir).into_iter().enumerate().skip(1) {
+        let expired = cutoff.is_some_and(|cutoff| {
+            fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|modified| modified < cutoff)
+        });
+        if position >= config.max_files.max(1) || expired {
+            if let Err(e) = fs::remove_file(&path) {
+                eprintln!("删除过期日志文件失败: {:?} - {}", path, e);
+            }
+        }
+    }
+}
+
+/// The last `max_lines` lines across the newest log files, oldest first.
+pub fn recent_log_lines(dir: &Path, max_lines: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (path, _, _) in log_files(dir) {
+        let Ok(content) = fs::read(&path) else {
+            continue;
+        };
+        let content = String::from_utf8_lossy(&content);
+        let mut file_lines: Vec<String> = content.lines().map(str::to_string).collect();
+        let keep = max_lines.saturating_sub(lines.len());
+        let skip = file_lines.len().saturating_sub(keep);
+        file_lines.drain(..skip);
+        // 更早的文件在前
+        file_lines.append(&mut lines);
+        lines = file_lines;
+        if lines.len() >= max_lines {
+            break;
+        }
+    }
+    lines
+}
+
+fn log_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path().app_log_dir().map_err(|e| e.to_string())
+}
+
+#[command]
+pub fn list_log_files(app: AppHandle) -> Result<Vec<LogFileInfo>, String> {
+    let dir = log_dir(&app)?;
+    Ok(log_files(&dir)
+        .into_iter()
+        .filter_map(|(path, _, _)| {
+            let metadata = fs::metadata(&path).ok()?;
+            Some(LogFileInfo {
+                name: path.file_name()?.to_string_lossy().into_owned(),
+                size: metadata.len(),
+                modified: metadata
+                    .modified()
+                    .map(|modified| {
+                        DateTime::<Local>::from(modified)
+                            .format("%Y-%m-%d %H:%M:%S")
+                            .to_string()
+                    })
+                    .unwrap_or_default(),
+            })
+        })
+        .collect())
+}
+
+#[command]
+pub fn read_log_file(app: AppHandle, name: String) -> Result<String, String> {
+    // Only names we generate, so the page cannot read arbitrary paths.
+    if parse_file_name(&name).is_none() {
+        return Err(format!("无效的日志文件名: {}", name));
+    }
+    let content = fs::read(log_dir(&app)?.join(&name)).map_err(|e| e.to_string())?;
+    Ok(String::from_utf8_lossy(&content).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn touch(dir: &Path, name: &str, age_days: u64) {
+        let file = File::create(dir.join(name)).unwrap();
+        let modified = SystemTime::now() - Duration::from_secs(age_days * 24 * 60 * 60);
+        file.set_modified(modified).unwrap();
+    }
+
+    fn remaining(dir: &Path) -> Vec<String> {
+        log_files(dir)
+            .into_iter()
+            .map(|(path, _, _)| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn parses_generated_names() {
+        assert_eq!(parse_file_name("pake-2024-05-01.log"), Some((date("2024-05-01"), 0)));
+        assert_eq!(parse_file_name("pake-2024-05-01.3.log"), Some((date("2024-05-01"), 3)));
+        for index in [0, 1, 12] {
+            let name = file_name(date("2024-12-31"), index);
+            assert_eq!(parse_file_name(&name), Some((date("2024-12-31"), index)));
+        }
+    }
+
+    #[test]
+    fn rejects_foreign_names() {
+        for name in [
+            "pake-2024-05-01.txt",
+            "other-2024-05-01.log",
+            "pake-2024-13-01.log",
+            "pake-2024-05-01.x.log",
+            "pake-2024-05-01.-1.log",
+            "pake-.log",
+            "../pake-2024-05-01.log",
+        ] {
+            assert_eq!(parse_file_name(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn lists_newest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "pake-2024-05-01.log",
+            "pake-2024-05-02.log",
+            "pake-2024-05-01.1.log",
+            "notes.txt",
+        ] {
+            touch(dir.path(), name, 0);
+        }
+        assert_eq!(
+            remaining(dir.path()),
+            ["pake-2024-05-02.log", "pake-2024-05-01.1.log", "pake-2024-05-01.log"]
+        );
+        assert_eq!(latest_index(dir.path(), date("2024-05-01")), 1);
+        assert_eq!(latest_index(dir.path(), date("2024-05-03")), 0);
+    }
+
+    #[test]
+    fn prunes_beyond_max_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for day in 1..=5 {
+            touch(dir.path(), &format!("pake-2024-05-0{}.log", day), 0);
+        }
+        let config = LogFileConfig {
+            max_files: 3,
+            retention_days: 0,
+            ..LogFileConfig::default()
+        };
+        prune_log_files(dir.path(), &config);
+        assert_eq!(
+            remaining(dir.path()),
+            ["pake-2024-05-05.log", "pake-2024-05-04.log", "pake-2024-05-03.log"]
+        );
+    }
+
+    #[test]
+    fn prunes_expired_files_but_keeps_current() {
+        let dir = tempfile::tempdir().unwrap();
+        touch(dir.path(), "pake-2024-05-01.log", 30);
+        touch(dir.path(), "pake-2024-05-02.log", 2);
+        touch(dir.path(), "pake-2024-05-03.log", 10);
+        let config = LogFileConfig {
+            max_files: 10,
+            retention_days: 7,
+            ..LogFileConfig::default()
+        };
+        prune_log_files(dir.path(), &config);
+        // 最新的文件正在写入，即使过期也保留
+        assert_eq!(
+            remaining(dir.path()),
+            ["pake-2024-05-03.log", "pake-2024-05-02.log"]
+        );
+    }
+
+    #[test]
+    fn zero_max_files_keeps_current() {
+        let dir = tempfile::tempdir().unwrap();
+        touch(dir.path(), "pake-2024-05-01.log", 0);
+        touch(dir.path(), "pake-2024-05-01.1.log", 0);
+        let config = LogFileConfig {
+            max_files: 0,
+            retention_days: 0,
+            ..LogFileConfig::default()
+        };
+        prune_log_files(dir.path(), &config);
+        assert_eq!(remaining(dir.path()), ["pake-2024-05-01.1.log"]);
+    }
+}
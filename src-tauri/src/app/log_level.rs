@@ -0,0 +1,142 @@
+use crate::app::config::LogLevelConfig;
+use crate::app::invoke::{add_log_entry, LogLevel, LogSource};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::RwLock;
+use tauri::command;
+
+// 所有来源和模块中最低的级别，低于它的日志可以直接丢弃，不用再查覆盖规则
+static LOWEST_LOG_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+// 当前生效的级别配置，set_log_level 修改后立即作用于缓冲区和日志文件
+static LOG_LEVELS: once_cell::sync::Lazy<RwLock<LogLevelConfig>> =
+    once_cell::sync::Lazy::new(|| RwLock::new(LogLevelConfig::default()));
+
+pub const LOG_SOURCES: [&str; 3] = ["rust", "webview", "network"];
+
+/// Invalid levels are skipped here; `validate_config` reports them.
+fn parse_level(level: &str) -> Option<LogLevel> {
+    LogLevel::from_str(level).ok()
+}
+
+/// Whether `prefix` names `target` or one of its parents, e.g. `reqwest` for
+/// `reqwest::connect`.
+fn target_matches(prefix: &str, target: &str) -> bool {
+    target
+        .strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+impl LogLevelConfig {
+    /// Override for the most specific configured target, i.e. the longest matching prefix.
+    fn target_level(&self, target: &str) -> Option<LogLevel> {
+        self.targets
+            .iter()
+            .filter(|(prefix, _)| target_matches(prefix, target))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .and_then(|(_, level)| parse_level(level))
+    }
+
+    /// Target override first, then source override, then the default level.
+    fn level_for(&self, source: LogSource, target: &str) -> LogLevel {
+        self.target_level(target)
+            .or_else(|| self.sources.get(source.as_str()).and_then(|level| parse_level(level)))
+            .or_else(|| parse_level(&self.level))
+            .unwrap_or(LogLevel::Info)
+    }
+
+    fn lowest(&self) -> LogLevel {
+        std::iter::once(&self.level)
+            .chain(self.sources.values())
+            .chain(self.targets.values())
+            .filter_map(|level| parse_level(level))
+            .min()
+            .unwrap_or(LogLevel::Info)
+    }
+}
+
+/// Applies `update` to the levels in effect and returns the result.
+fn update_log_levels(update: impl FnOnce(&mut LogLevelConfig)) -> LogLevelConfig {
+    let mut levels = LOG_LEVELS.write().unwrap();
+    update(&mut levels);
+    let lowest = levels.lowest();
+    LOWEST_LOG_LEVEL.store(lowest as u8, Ordering::Relaxed);
+    // 同步 log 门面的级别，低于该级别的 log:: 调用不会被格式化
+    log::set_max_level(match lowest {
+        LogLevel::Trace => log::LevelFilter::Trace,
+        LogLevel::Debug => log::LevelFilter::Debug,
+        LogLevel::Info => log::LevelFilter::Info,
+        LogLevel::Warn => log::LevelFilter::Warn,
+        LogLevel::Error => log::LevelFilter::Error,
+    });
+    levels.clone()
+}
+
+/// Replaces the levels in effect, e.g. once `pake.json` and the CLI have been read.
+pub fn configure_log_levels(config: &LogLevelConfig) {
+    update_log_levels(|levels| *levels = config.clone());
+}
+
+/// Whether an entry at `level` from `source` / `target` should be recorded.
+pub(crate) fn log_enabled(level: LogLevel, source: LogSource, target: &str) -> bool {
+    if (level as u8) < LOWEST_LOG_LEVEL.load(Ordering::Relaxed) {
+        return false;
+    }
+    level >= LOG_LEVELS.read().unwrap().level_for(source, target)
+}
+
+/// Level configured for `target` itself or one of its parents, if any.
+pub(crate) fn target_log_level(target: &str) -> Option<LogLevel> {
+    LOG_LEVELS.read().unwrap().target_level(target)
+}
+
+/// Tauri Command: 运行时调整日志级别，立即生效，重启后恢复配置中的级别。
+/// 同时省略 `source` 和 `target` 时修改默认级别；`level` 为 `null` 时删除对应的覆盖。
+/// 返回修改后的完整配置。
+#[command]
+pub fn set_log_level(
+    level: Option<String>,
+    source: Option<String>,
+    target: Option<String>,
+) -> Result<LogLevelConfig, String> {
+    let level = level
+        .map(|level| LogLevel::from_str(&level).map(|level| level.as_str().to_lowercase()))
+        .transpose()?;
+    let source = source.map(|source| source.to_lowercase());
+    if let Some(source) = &source {
+        if !LOG_SOURCES.contains(&source.as_str()) {
+            return Err(format!("未知的日志来源 {}，只能是 {}", source, LOG_SOURCES.join(" / ")));
+        }
+    }
+    let scope = match (&source, &target) {
+        (Some(_), Some(_)) => return Err("source 和 target 不能同时指定".to_string()),
+        (None, Some(target)) if target.is_empty() => return Err("target 不能为空".to_string()),
+        (None, None) if level.is_none() => return Err("默认级别不能删除".to_string()),
+        (Some(source), None) => format!("来源 {}", source),
+        (None, Some(target)) => format!("模块 {}", target),
+        (None, None) => "默认".to_string(),
+    };
+
+    let config = update_log_levels(|config| {
+        let overrides = match (source, target) {
+            (Some(source), _) => Some((&mut config.sources, source)),
+            (_, Some(target)) => Some((&mut config.targets, target)),
+            _ => None,
+        };
+        match (overrides, level.clone()) {
+            (Some((overrides, key)), Some(level)) => {
+                overrides.insert(key, level);
+            }
+            (Some((overrides, key)), None) => {
+                overrides.remove(&key);
+            }
+            (None, Some(level)) => config.level = level,
+            (None, None) => {}
+        }
+    });
+    add_log_entry(
+        "INFO",
+        &format!("日志级别已修改: {} -> {}", scope, level.as_deref().unwrap_or("（删除覆盖）")),
+    );
+    Ok(config)
+}
@@ -0,0 +1,237 @@
+use crate::app::invoke::{with_log_buffer, LogEntry, LogLevel, LogSource};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{command, AppHandle, Emitter, Manager, WebviewWindow};
+
+/// Batches of new entries sent to subscribed webviews.
+pub const LOG_EVENT: &str = "pake://log";
+
+// 每个订阅者最多积压的条目数，超出后丢弃并计数
+const STREAM_CAPACITY: usize = 1000;
+const BATCH_INTERVAL: Duration = Duration::from_millis(200);
+const MAX_BATCH_SIZE: usize = 200;
+
+// 当前的订阅者，每个 webview 最多一个
+static SUBSCRIBERS: once_cell::sync::Lazy<Mutex<Vec<Subscriber>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(Vec::new()));
+
+struct Subscriber {
+    label: String,
+    filter: LogSubscription,
+    sender: SyncSender<LogEntry>,
+    dropped: Arc<AtomicU64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LogSubscription {
+    pub min_level: Option<LogLevel>,
+    /// Empty streams every source.
+    pub sources: Vec<LogSource>,
+}
+
+impl LogSubscription {
+    fn matches(&self, entry: &LogEntry) -> bool {
+        self.min_level.map_or(true, |level| entry.level >= level)
+            && (self.sources.is_empty() || self.sources.contains(&entry.source))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogBatch {
+    pub entries: Vec<LogEntry>,
+    /// Entries dropped since the previous batch because the webview fell behind.
+    pub dropped: u64,
+}
+
+/// Hands `entry` to every matching subscriber. A `push_log_entry` sink, see the rules there.
+pub fn dispatch_log_entry(entry: &LogEntry) {
+    let subscribers = SUBSCRIBERS.lock().unwrap();
+    for subscriber in subscribers.iter().filter(|s| s.filter.matches(entry)) {
+        match subscriber.sender.try_send(entry.clone()) {
+            Ok(_) | Err(TrySendError::Disconnected(_)) => {}
+            Err(TrySendError::Full(_)) => {
+                subscriber.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Forwards entries to one webview in batches until it unsubscribes or closes.
+fn forward(app: AppHandle, label: String, receiver: Receiver<LogEntry>, dropped: Arc<AtomicU64>) {
+    while let Some(entries) = next_batch(&receiver) {
+        if app.get_webview_window(&label).is_none() {
+            remove_subscriber(&label);
+            return;
+        }
+
+        let dropped = dropped.swap(0, Ordering::Relaxed);
+        if entries.is_empty() && dropped == 0 {
+            continue;
+        }
+        if let Err(e) = app.emit_to(label.as_str(), LOG_EVENT, LogBatch { entries, dropped }) {
+            // 这里记录日志会再次推送给同一个订阅者
+            eprintln!("推送日志到 {} 失败: {}", label, e);
+        }
+    }
+}
+
+/// Waits up to [`BATCH_INTERVAL`] for at most [`MAX_BATCH_SIZE`] entries. Returns `None`
+/// once the subscriber was removed or replaced.
+fn next_batch(receiver: &Receiver<LogEntry>) -> Option<Vec<LogEntry>> {
+    let mut entries = Vec::new();
+    let deadline = Instant::now() + BATCH_INTERVAL;
+    while entries.len() < MAX_BATCH_SIZE {
+        match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(entry) => entries.push(entry),
+            Err(RecvTimeoutError::Timeout) => break,
+            Err(RecvTimeoutError::Disconnected) => return None,
+        }
+    }
+    Some(entries)
+}
+
+fn add_subscriber(label: &str, filter: LogSubscription) -> (Receiver<LogEntry>, Arc<AtomicU64>) {
+    let (sender, receiver) = sync_channel(STREAM_CAPACITY);
+    let dropped = Arc::new(AtomicU64::new(0));
+    let mut subscribers = SUBSCRIBERS.lock().unwrap();
+    // 替换旧订阅时其 sender 被释放，转发线程随之退出
+    subscribers.retain(|subscriber| subscriber.label != label);
+    subscribers.push(Subscriber {
+        label: label.to_string(),
+        filter,
+        sender,
+        dropped: dropped.clone(),
+    });
+    (receiver, dropped)
+}
+
+fn remove_subscriber(label: &str) -> bool {
+    let mut subscribers = SUBSCRIBERS.lock().unwrap();
+    let count = subscribers.len();
+    subscribers.retain(|subscriber| subscriber.label != label);
+    subscribers.len() != count
+}
+
+/// Starts streaming [`LOG_EVENT`] to the calling webview, replacing its previous
+/// subscription. Returns the newest `seq` so the page can load older entries with
+/// `get_logs` and skip duplicates.
+#[command]
+pub fn subscribe_logs(
+    app: AppHandle,
+    window: WebviewWindow,
+    filter: Option<LogSubscription>,
+) -> Result<u64, String> {
+    let label = window.label().to_string();
+    let (receiver, dropped) = add_subscriber(&label, filter.unwrap_or_default());
+
+    let thread_label = label.clone();
+    std::thread::Builder::new()
+        .name(format!("pake-log-stream-{}", label))
+        .spawn(move || forward(app, thread_label, receiver, dropped))
+        .map_err(|e| {
+            remove_subscriber(&label);
+            e.to_string()
+        })?;
+
+    Ok(with_log_buffer(|entries| entries.back().map_or(0, |entry| entry.seq)))
+}
+
+#[command]
+pub fn unsubscribe_logs(window: WebviewWindow) -> bool {
+    remove_subscriber(window.label())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::invoke::LogFields;
+
+    // 订阅者列表是全局的，这里的测试依次运行；其他测试不会产生 network 条目
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    fn network_only() -> LogSubscription {
+        LogSubscription {
+            min_level: None,
+            sources: vec![LogSource::Network],
+        }
+    }
+
+    fn entry(seq: u64, level: LogLevel) -> LogEntry {
+        LogEntry {
+            seq,
+            timestamp: 0,
+            level,
+            source: LogSource::Network,
+            target: "log_stream_test".to_string(),
+            message: format!("request {}", seq),
+            fields: LogFields::new(),
+        }
+    }
+
+    #[test]
+    fn queue_is_bounded_and_counts_dropped_entries() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let (receiver, dropped) = add_subscriber("test-bounded", network_only());
+        for seq in 0..STREAM_CAPACITY as u64 + 5 {
+            dispatch_log_entry(&entry(seq, LogLevel::Info));
+        }
+        assert_eq!(dropped.load(Ordering::Relaxed), 5);
+
+        let first = next_batch(&receiver).unwrap();
+        assert_eq!(first.len(), MAX_BATCH_SIZE);
+        assert_eq!(first[0].seq, 0);
+        let rest: Vec<LogEntry> = receiver.try_iter().collect();
+        assert_eq!(rest.len(), STREAM_CAPACITY - MAX_BATCH_SIZE);
+        assert_eq!(rest.last().unwrap().seq, STREAM_CAPACITY as u64 - 1);
+
+        // 队列腾出空间后不再丢弃
+        dispatch_log_entry(&entry(9999, LogLevel::Info));
+        assert_eq!(dropped.load(Ordering::Relaxed), 5);
+        assert_eq!(next_batch(&receiver).unwrap().len(), 1);
+        assert!(remove_subscriber("test-bounded"));
+    }
+
+    #[test]
+    fn only_matching_entries_are_queued() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let filter = LogSubscription {
+            min_level: Some(LogLevel::Warn),
+            ..network_only()
+        };
+        let (receiver, dropped) = add_subscriber("test-filter", filter);
+        for (seq, level) in [(1, LogLevel::Debug), (2, LogLevel::Warn), (3, LogLevel::Info), (4, LogLevel::Error)] {
+            dispatch_log_entry(&entry(seq, level));
+        }
+        let mut webview = entry(5, LogLevel::Error);
+        webview.source = LogSource::Webview;
+        dispatch_log_entry(&webview);
+
+        let seqs: Vec<u64> = next_batch(&receiver).unwrap().iter().map(|entry| entry.seq).collect();
+        assert_eq!(seqs, [2, 4]);
+        assert_eq!(dropped.load(Ordering::Relaxed), 0);
+        assert!(remove_subscriber("test-filter"));
+    }
+
+    #[test]
+    fn unsubscribing_or_resubscribing_ends_the_stream() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let (first, _) = add_subscriber("test-unsubscribe", network_only());
+        let (second, _) = add_subscriber("test-unsubscribe", network_only());
+        // 替换后旧的队列已断开
+        assert!(next_batch(&first).is_none());
+
+        dispatch_log_entry(&entry(1, LogLevel::Info));
+        assert!(remove_subscriber("test-unsubscribe"));
+        assert!(!remove_subscriber("test-unsubscribe"));
+
+        // 已排队的条目先取完，之后断开
+        assert_eq!(second.try_iter().count(), 1);
+        assert!(next_batch(&second).is_none());
+        dispatch_log_entry(&entry(2, LogLevel::Info));
+        assert!(second.try_recv().is_err());
+    }
+}
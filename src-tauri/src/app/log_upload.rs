@@ -0,0 +1,478 @@
+use crate::app::bundle::{BundleManifest, BundleWriter, LogSummary};
+use crate::app::config::{LogUploadConfig, PakeConfig};
+use crate::app::invoke::{add_log_entry, with_log_buffer, LogSource};
+use crate::app::otlp::CommandSpan;
+use crate::app::redact::redact_lines;
+use crate::app::report::{FeedbackReport, ReportRecord};
+use chrono::Utc;
+use reqwest::Client;
+use crate::app::upload_backend::{ProgressFn, UploadArtifact, UploadBackend, UploadResult};
+use crate::app::upload_queue::enqueue_upload;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{command, AppHandle, Emitter, Manager, State, WebviewWindow};
+use tokio_util::sync::CancellationToken;
+
+/// Progress of a running upload, sent to every webview.
+pub const UPLOAD_PROGRESS_EVENT: &str = "pake://upload-progress";
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+// 进行中的上传，按 upload_id 取消
+static ACTIVE_UPLOADS: once_cell::sync::Lazy<Mutex<HashMap<String, CancellationToken>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadProgress {
+    pub upload_id: String,
+    pub file_name: String,
+    /// 1-based index of the part being sent.
+    pub part: usize,
+    pub parts: usize,
+    /// Bytes sent across all parts, out of `total`.
+    pub bytes_sent: u64,
+    pub total: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogUploadResult {
+    pub upload_id: String,
+    /// Id of the feedback report, for the user to quote to support.
+    pub report_id: Option<String>,
+    /// One entry per uploaded file; more than one when the bundle was split.
+    pub parts: Vec<UploadResult>,
+    pub size: u64,
+    pub elapsed_ms: u64,
+}
+
+/// 从前端传递过来的日志数据结构
+#[derive(Deserialize)]
+pub struct LogsPayload {
+    runtime: Vec<String>,
+    xhr: Vec<String>,
+    /// Unix milliseconds of the oldest and newest entry, recorded in the manifest.
+    #[serde(default)]
+    from: Option<i64>,
+    #[serde(default)]
+    to: Option<i64>,
+}
+
+impl LogsPayload {
+    /// The Rust-side buffer, which also holds what the page sent through `push_logs`.
+    /// Used when there is no page to collect logs from, e.g. exporting from the tray.
+    pub(crate) fn from_log_buffer() -> Self {
+        with_log_buffer(|entries| {
+            let (xhr, runtime): (Vec<_>, Vec<_>) =
+                entries.iter().partition(|entry| entry.source == LogSource::Network);
+            Self {
+                runtime: runtime.iter().map(|entry| entry.to_line()).collect(),
+                xhr: xhr.iter().map(|entry| entry.to_line()).collect(),
+                from: entries.front().map(|entry| entry.timestamp),
+                to: entries.back().map(|entry| entry.timestamp),
+            }
+        })
+    }
+
+    /// Lines from the log files, e.g. those of a previous run that crashed.
+    pub(crate) fn from_file_lines(runtime: Vec<String>) -> Self {
+        Self {
+            runtime,
+            xhr: Vec::new(),
+            from: None,
+            to: None,
+        }
+    }
+
+    fn summary(&self) -> LogSummary {
+        LogSummary {
+            from: self.from,
+            to: self.to,
+            runtime_entries: self.runtime.len(),
+            xhr_entries: self.xhr.len(),
+        }
+    }
+}
+
+/// `api_key_env`, then `api_key_file`, then `api_key`. `None` when none of them is set.
+fn resolve_api_key(app: &AppHandle, config: &LogUploadConfig) -> Result<Option<String>, String> {
+    if !config.api_key_env.is_empty() {
+        if let Some(key) = std::env::var(&config.api_key_env).ok().filter(|key| !key.is_empty()) {
+            return Ok(Some(key));
+        }
+    }
+
+    if !config.api_key_file.is_empty() {
+        let path = Path::new(&config.api_key_file);
+        let path = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            app.path()
+                .app_config_dir()
+                .map_err(|e| e.to_string())?
+                .join(path)
+        };
+        let key = std::fs::read_to_string(&path)
+            .map_err(|e| format!("无法读取密钥文件 {:?}: {}", path, e))?;
+        return Ok(Some(key.trim().to_string()));
+    }
+
+    Ok((!config.api_key.is_empty()).then(|| config.api_key.clone()))
+}
+
+/// Backend for the current `log_upload` config, with the API key resolved.
+pub(crate) fn configured_backend(
+    app: &AppHandle,
+    config: &LogUploadConfig,
+) -> Result<UploadBackend, String> {
+    let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    UploadBackend::from_config(config, resolve_api_key(app, config)?, &config_dir)
+}
+
+fn write_members<W: Write + Seek>(bundle: &mut BundleWriter<W>, job: &BundleJob) -> Result<(), String> {
+    bundle.add_lines("runtime.log", &job.logs.runtime)?;
+    bundle.add_lines("xhr.log", &job.logs.xhr)?;
+    if let Some(report) = &job.report {
+        report.write_to(bundle)?;
+    }
+    for (name, data) in &job.extra_files {
+        bundle.add(name, |writer| writer.write_all(data))?;
+    }
+    Ok(())
+}
+
+/// Writes the bundle to `path`. With a `recipient` the zip is built in memory and written
+/// through age, so only the encrypted form ever reaches the disk.
+fn build_zip(mut job: BundleJob, path: &Path) -> Result<(), String> {
+    // 页面日志存在 IndexedDB 中，未经过 Rust 端脱敏，打包前再处理一遍
+    redact_lines(&mut job.logs.runtime, &mut job.manifest.redaction.upload);
+    redact_lines(&mut job.logs.xhr, &mut job.manifest.redaction.upload);
+
+    let manifest = job.manifest.clone();
+    let Some(recipient) = job.recipient.clone() else {
+        // 写入临时文件，避免大日志包整体驻留内存
+        let mut bundle = BundleWriter::create(path)?;
+        write_members(&mut bundle, &job)?;
+        return bundle.finish(manifest).map(|_| ());
+    };
+
+    // zip 需要回写目录，无法直接写入加密流，只能先在内存中生成
+    let mut bundle = BundleWriter::in_memory();
+    write_members(&mut bundle, &job)?;
+    let zip = bundle.finish(manifest)?.into_inner();
+
+    let encryptor = age::Encryptor::with_recipients(std::iter::once(&recipient as &dyn age::Recipient))
+        .map_err(|e| format!("Failed to encrypt logs: {}", e))?;
+    let file = File::create(path).map_err(|e| format!("Failed to create encrypted file: {}", e))?;
+    let mut writer = encryptor
+        .wrap_output(BufWriter::new(file))
+        .map_err(|e| format!("Failed to encrypt logs: {}", e))?;
+    writer.write_all(&zip).map_err(|e| format!("Failed to encrypt logs: {}", e))?;
+    writer
+        .finish()
+        .and_then(|mut file| file.flush())
+        .map_err(|e| format!("Failed to encrypt logs: {}", e))
+}
+
+/// Everything needed to write one bundle, collected before handing off to a blocking thread.
+pub(crate) struct BundleJob {
+    pub file_name: String,
+    pub report_id: Option<String>,
+    logs: LogsPayload,
+    report: Option<ReportRecord>,
+    manifest: BundleManifest,
+    recipient: Option<age::x25519::Recipient>,
+    /// Additional members such as crash reports, written after the logs.
+    extra_files: Vec<(String, Vec<u8>)>,
+}
+
+impl BundleJob {
+    pub(crate) fn prepare(
+        app: &AppHandle,
+        config: &PakeConfig,
+        logs: LogsPayload,
+        report: Option<ReportRecord>,
+    ) -> Result<Self, String> {
+        let recipient = match config.log_upload.recipient.trim() {
+            "" => None,
+            key => Some(
+                age::x25519::Recipient::from_str(key)
+                    .map_err(|e| format!("log_upload.recipient 无效: {}", e))?,
+            ),
+        };
+        let report_id = report.as_ref().map(|report| report.report_id.clone());
+
+        // 生成带时间戳的文件名，格式为 <product_name>_YYYYMMDDHHMMSS.zip，确保文件名唯一性。
+        let product_name = app
+            .config()
+            .product_name
+            .clone()
+            .unwrap_or_else(|| "pake_logs".to_string());
+        let timestamp = Utc::now().format("%Y%m%d%H%M%S");
+        let mut file_name = match &report_id {
+            Some(report_id) => format!("{}_{}_{}.zip", product_name, timestamp, report_id),
+            None => format!("{}_{}.zip", product_name, timestamp),
+        };
+        // 加密后的文件使用 age 的扩展名
+        if recipient.is_some() {
+            file_name.push_str(".age");
+        }
+
+        let mut manifest = BundleManifest::collect(app, config, logs.summary());
+        manifest.report_id = report_id.clone();
+        Ok(Self {
+            file_name,
+            report_id,
+            logs,
+            report,
+            manifest,
+            recipient,
+            extra_files: Vec::new(),
+        })
+    }
+
+    pub(crate) fn add_file(&mut self, name: String, data: Vec<u8>) {
+        self.extra_files.push((name, data));
+    }
+
+    /// Writes the bundle to `path` on a blocking thread; a partial file is removed on failure.
+    pub(crate) async fn write(self, path: &Path) -> Result<(), String> {
+        let target = path.to_path_buf();
+        let result = tauri::async_runtime::spawn_blocking(move || build_zip(self, &target))
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|result| result);
+        if result.is_err() {
+            let _ = std::fs::remove_file(path);
+        }
+        result
+    }
+}
+
+/// Splits a bundle above `max_part_size` into `name.zip.001`, `name.zip.002`, ... next to it.
+/// The parts are a plain byte split; `cat name.zip.* > name.zip` restores the archive.
+fn split_bundle(path: &Path, file_name: &str, max_part_size: u64) -> Result<Vec<UploadArtifact>, String> {
+    let bundle = UploadArtifact::from_file(file_name.to_string(), path.to_path_buf())?;
+    if max_part_size == 0 || bundle.size <= max_part_size {
+        return Ok(vec![bundle]);
+    }
+
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut reader = File::open(path).map_err(|e| e.to_string())?;
+    let mut parts = Vec::new();
+    for index in 1..=bundle.size.div_ceil(max_part_size) {
+        let part_name = format!("{}.{:03}", file_name, index);
+        let part_path = dir.join(&part_name);
+        let mut writer = File::create(&part_path).map_err(|e| e.to_string())?;
+        std::io::copy(&mut (&mut reader).take(max_part_size), &mut writer)
+            .map_err(|e| format!("Failed to split zip: {}", e))?;
+        parts.push(UploadArtifact::from_file(part_name, part_path)?);
+    }
+    let _ = std::fs::remove_file(path);
+    Ok(parts)
+}
+
+/// Emits [`UPLOAD_PROGRESS_EVENT`] for one part, at most every [`PROGRESS_INTERVAL`] plus
+/// once when the part is done. `offset` is the size of the parts already sent.
+fn progress_reporter(
+    app: &AppHandle,
+    upload_id: &str,
+    artifact: &UploadArtifact,
+    (part, parts): (usize, usize),
+    offset: u64,
+    total: u64,
+) -> ProgressFn {
+    let app = app.clone();
+    let upload_id = upload_id.to_string();
+    let file_name = artifact.file_name.clone();
+    let part_size = artifact.size;
+    let last_emit: Mutex<Option<Instant>> = Mutex::new(None);
+
+    Arc::new(move |sent| {
+        let mut last_emit = last_emit.lock().unwrap();
+        if sent < part_size && last_emit.is_some_and(|at| at.elapsed() < PROGRESS_INTERVAL) {
+            return;
+        }
+        *last_emit = Some(Instant::now());
+        let _ = app.emit(
+            UPLOAD_PROGRESS_EVENT,
+            UploadProgress {
+                upload_id: upload_id.clone(),
+                file_name: file_name.clone(),
+                part,
+                parts,
+                bytes_sent: offset + sent,
+                total,
+            },
+        );
+    })
+}
+
+/// Registers a cancellable upload; dropping it unregisters.
+struct ActiveUpload {
+    id: String,
+    token: CancellationToken,
+}
+
+impl ActiveUpload {
+    fn register(id: &str) -> Result<Self, String> {
+        let mut uploads = ACTIVE_UPLOADS.lock().unwrap();
+        if uploads.contains_key(id) {
+            return Err(format!("上传 {} 正在进行中", id));
+        }
+        let token = CancellationToken::new();
+        uploads.insert(id.to_string(), token.clone());
+        Ok(Self {
+            id: id.to_string(),
+            token,
+        })
+    }
+}
+
+impl Drop for ActiveUpload {
+    fn drop(&mut self) {
+        ACTIVE_UPLOADS.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Queues the parts that were not uploaded and returns the error for the page.
+fn queue_remaining(app: &AppHandle, remaining: &[UploadArtifact], error: String) -> String {
+    let mut queued = true;
+    for artifact in remaining {
+        if let Err(queue_error) = enqueue_upload(app, artifact, &error) {
+            add_log_entry("ERROR", &format!("加入待上传队列失败: {}", queue_error));
+            queued = false;
+        }
+    }
+    if queued {
+        format!("{}，已加入待上传队列，稍后自动重试", error)
+    } else {
+        error
+    }
+}
+
+/// Tauri Command: 处理日志上传
+/// 接收前端发送的运行时日志和XHR日志（以及可选的问题反馈），将它们打包成ZIP文件，交给 `log_upload.backend` 上传。
+/// 超过 `max_part_size` 的压缩包分片上传，进度通过 [`UPLOAD_PROGRESS_EVENT`] 推送，
+/// 可以用 [`cancel_log_upload`] 取消。
+#[command]
+pub async fn handle_log_upload(
+    app: AppHandle,
+    window: WebviewWindow,
+    config: State<'_, PakeConfig>,
+    logs: LogsPayload,
+    report: Option<FeedbackReport>,
+    upload_id: Option<String>,
+) -> Result<LogUploadResult, String> {
+    let span = CommandSpan::start("handle_log_upload")
+        .attribute("pake.window", window.label())
+        .attribute("pake.upload.backend", format!("{:?}", config.log_upload.backend).to_lowercase());
+    let result = async {
+        if !config.log_upload.is_enabled() {
+            return Err(
+                "日志上传未配置：请在 pake.json 的 log_upload 中设置 endpoint（local 后端为 directory）"
+                    .to_string(),
+            );
+        }
+        let report = report
+            .map(|report| report.validate(window.url().ok().map(|url| url.to_string())))
+            .transpose()?;
+        let job = BundleJob::prepare(&app, &config, logs, report)?;
+        upload_bundle(&app, &config.log_upload, job, upload_id).await
+    }
+    .await;
+    span.finish(result)
+}
+
+/// Writes `job` to a temp dir and sends it with the configured backend, split into parts
+/// when needed. Parts that fail are queued for retry.
+pub(crate) async fn upload_bundle(
+    app: &AppHandle,
+    upload: &LogUploadConfig,
+    job: BundleJob,
+    upload_id: Option<String>,
+) -> Result<LogUploadResult, String> {
+    let backend = configured_backend(app, upload)?;
+    let started = Instant::now();
+    let file_name = job.file_name.clone();
+    let report_id = job.report_id.clone();
+    let upload_id = upload_id.unwrap_or_else(|| file_name.split(".zip").next().unwrap_or_default().to_string());
+    let active = ActiveUpload::register(&upload_id)?;
+
+    // 临时目录在函数返回时删除，失败的分片会先复制到待上传队列
+    let temp_dir = tempfile::Builder::new()
+        .prefix("pake-upload-")
+        .tempdir()
+        .map_err(|e| format!("无法创建临时目录: {}", e))?;
+    let zip_path = temp_dir.path().join(&file_name);
+    job.write(&zip_path).await?;
+    let parts = split_bundle(&zip_path, &file_name, upload.max_part_size)?;
+    let total: u64 = parts.iter().map(|part| part.size).sum();
+
+    let client = Client::new();
+    let mut results = Vec::with_capacity(parts.len());
+    let mut offset = 0;
+    for (index, part) in parts.iter().enumerate() {
+        let progress = progress_reporter(app, &upload_id, part, (index + 1, parts.len()), offset, total);
+        let outcome = tokio::select! {
+            outcome = backend.upload(&client, part, progress) => outcome,
+            _ = active.token.cancelled() => {
+                // 用户主动取消，不加入待上传队列
+                add_log_entry("WARN", &format!("日志上传已取消: {}", upload_id));
+                return Err("上传已取消".to_string());
+            }
+        };
+        match outcome {
+            Ok(result) => {
+                offset += part.size;
+                results.push(result);
+            }
+            Err(e) => {
+                add_log_entry("ERROR", &format!("日志上传失败: {} - {}", part.file_name, e));
+                let error = queue_remaining(app, &parts[index..], e);
+                return Err(match &report_id {
+                    Some(report_id) => format!("{}（报告编号 {}）", error, report_id),
+                    None => error,
+                });
+            }
+        }
+    }
+
+    let result = LogUploadResult {
+        upload_id,
+        report_id,
+        parts: results,
+        size: total,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    };
+    add_log_entry(
+        "INFO",
+        &format!(
+            "日志上传成功: {} ({} 个文件, {} 字节, {} ms)",
+            file_name,
+            result.parts.len(),
+            result.size,
+            result.elapsed_ms
+        ),
+    );
+    Ok(result)
+}
+
+/// Aborts the upload with `upload_id`, or every running upload when omitted.
+/// Returns whether anything was cancelled.
+#[command]
+pub fn cancel_log_upload(upload_id: Option<String>) -> bool {
+    let uploads = ACTIVE_UPLOADS.lock().unwrap();
+    let mut cancelled = false;
+    for (id, token) in uploads.iter() {
+        if upload_id.as_deref().map_or(true, |upload_id| upload_id == id) {
+            token.cancel();
+            cancelled = true;
+        }
+    }
+    cancelled
+}
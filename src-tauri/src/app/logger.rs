@@ -0,0 +1,66 @@
+use crate::app::invoke::{push_log_entry, LogFields, LogSource};
+use crate::app::log_level::{log_enabled, target_log_level};
+use log::{Level, Log, Metadata, Record};
+
+static LOGGER: PakeLogger = PakeLogger;
+
+/// Sends `log::` records from our code and from dependencies to the same buffer and log
+/// file as `add_log_entry`, keeping the module path as the entry target.
+struct PakeLogger;
+
+impl PakeLogger {
+    fn is_own_target(target: &str) -> bool {
+        target == "app_lib"
+            || target.starts_with("app_lib::")
+            || target == "pake"
+            || target.starts_with("pake::")
+    }
+}
+
+impl Log for PakeLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // Dependencies (tao, wry, reqwest, ...) are far too chatty below INFO, unless a
+        // level was set for them explicitly.
+        let target = metadata.target();
+        if !Self::is_own_target(target) && metadata.level() > Level::Info && target_log_level(target).is_none() {
+            return false;
+        }
+        log_enabled(metadata.level().into(), LogSource::Rust, target)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            push_log_entry(
+                record.level().into(),
+                LogSource::Rust,
+                record.target(),
+                record.args().to_string(),
+                LogFields::new(),
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs the logger; only the first call has any effect.
+pub fn init_logger() {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(log::LevelFilter::Info);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn own_targets_are_whole_crate_paths() {
+        for target in ["app_lib", "app_lib::app::log_upload", "pake", "pake::util"] {
+            assert!(PakeLogger::is_own_target(target), "{}", target);
+        }
+        for target in ["app_library", "pakefile", "pake_cli::run", "reqwest", "tao::platform"] {
+            assert!(!PakeLogger::is_own_target(target), "{}", target);
+        }
+    }
+}
@@ -1,4 +1,26 @@
+pub mod args;
+pub mod bundle;
 pub mod config;
+pub mod crash;
+pub mod deep_link;
+pub mod inject;
 pub mod invoke;
+pub mod launch;
+pub mod log_export;
+pub mod log_file;
+pub mod log_level;
+pub mod log_stream;
+pub mod log_upload;
+pub mod logger;
+pub mod otlp;
+pub mod profile;
+pub mod redact;
+pub mod report;
 pub mod setup;
+#[cfg(test)]
+pub mod stub_server;
+pub mod syslog;
+pub mod upload_backend;
+pub mod upload_queue;
+pub mod validate;
 pub mod window;
@@ -0,0 +1,565 @@
+use crate::app::config::{OtlpConfig, PakeConfig};
+use crate::app::invoke::{add_log_entry, with_log_buffer, LogEntry, LogLevel};
+use crate::app::redact::redact_recorded;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::Client;
+use serde_json::{json, Map, Value};
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+use tokio::sync::mpsc::{channel, error::TrySendError, Receiver, Sender};
+
+const LOGS_PATH: &str = "/v1/logs";
+const TRACES_PATH: &str = "/v1/traces";
+const SCOPE_NAME: &str = "pake";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+static OTLP: once_cell::sync::OnceCell<OtlpSink> = once_cell::sync::OnceCell::new();
+
+// 队列已满时丢弃的记录数，下次导出成功后记录一条警告
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+
+struct OtlpSink {
+    sender: Sender<Telemetry>,
+    logs: bool,
+    traces: bool,
+    min_level: LogLevel,
+}
+
+enum Telemetry {
+    Log(LogEntry),
+    Span(SpanRecord),
+}
+
+struct SpanRecord {
+    trace_id: String,
+    span_id: String,
+    name: &'static str,
+    start: u128,
+    end: u128,
+    attributes: Vec<Value>,
+    error: Option<String>,
+}
+
+fn unix_nanos(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_nanos())
+}
+
+/// Lowercase hex of `bytes` random bytes, as OTLP/JSON encodes trace and span ids.
+fn random_id(bytes: usize) -> String {
+    uuid::Uuid::new_v4().simple().to_string()[..bytes * 2].to_string()
+}
+
+/// JSON value as an OTLP `AnyValue`; 64-bit integers are encoded as strings.
+fn any_value(value: &Value) -> Value {
+    match value {
+        Value::String(text) => json!({ "stringValue": text }),
+        Value::Bool(flag) => json!({ "boolValue": flag }),
+        Value::Number(number) if number.is_i64() || number.is_u64() => json!({ "intValue": number.to_string() }),
+        Value::Number(number) => json!({ "doubleValue": number.as_f64() }),
+        Value::Array(items) => json!({ "arrayValue": { "values": items.iter().map(any_value).collect::<Vec<_>>() } }),
+        Value::Object(map) => json!({ "kvlistValue": { "values": key_values(map) } }),
+        Value::Null => json!({}),
+    }
+}
+
+fn key_value(key: &str, value: impl Into<Value>) -> Value {
+    json!({ "key": key, "value": any_value(&value.into()) })
+}
+
+fn key_values(map: &Map<String, Value>) -> Vec<Value> {
+    map.iter().map(|(key, value)| key_value(key, value.clone())).collect()
+}
+
+/// Times one command call and exports it as a span once finished, when traces are enabled.
+pub struct CommandSpan {
+    name: &'static str,
+    start: SystemTime,
+    attributes: Vec<Value>,
+}
+
+impl CommandSpan {
+    pub fn start(name: &'static str) -> Self {
+        Self {
+            name,
+            start: SystemTime::now(),
+            attributes: vec![key_value("pake.command", name)],
+        }
+    }
+
+    pub fn attribute(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.attributes.push(key_value(key, value));
+        self
+    }
+
+    /// Exports the span with the outcome of `result`, which is passed through unchanged.
+    pub fn finish<T, E: Display>(self, result: Result<T, E>) -> Result<T, E> {
+        let Some(sink) = OTLP.get().filter(|sink| sink.traces) else {
+            return result;
+        };
+        let span = SpanRecord {
+            trace_id: random_id(16),
+            span_id: random_id(8),
+            name: self.name,
+            start: unix_nanos(self.start),
+            end: unix_nanos(SystemTime::now()),
+            attributes: self.attributes,
+            error: result.as_ref().err().map(|e| redact_recorded(e.to_string())),
+        };
+        if let Err(TrySendError::Full(_)) = sink.sender.try_send(Telemetry::Span(span)) {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+struct Exporter {
+    client: Client,
+    endpoint: String,
+    resource: Value,
+    scope: Value,
+    failing: bool,
+}
+
+impl Exporter {
+    fn new(config: &OtlpConfig, resource: Value) -> Result<Self, String> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        for (name, value) in &config.headers {
+            let name = HeaderName::from_str(name).map_err(|e| format!("无效的请求头 {}: {}", name, e))?;
+            let value = HeaderValue::from_str(value).map_err(|e| format!("无效的请求头 {}: {}", name, e))?;
+            headers.insert(name, value);
+        }
+        let client = Client::builder()
+            .default_headers(headers)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            client,
+            endpoint: config.endpoint.trim_end_matches('/').to_string(),
+            resource,
+            scope: json!({ "name": SCOPE_NAME, "version": env!("CARGO_PKG_VERSION") }),
+            failing: false,
+        })
+    }
+
+    fn log_record(entry: &LogEntry) -> Value {
+        let severity = match entry.level {
+            LogLevel::Trace => 1,
+            LogLevel::Debug => 5,
+            LogLevel::Info => 9,
+            LogLevel::Warn => 13,
+            LogLevel::Error => 17,
+        };
+        let mut attributes = vec![
+            key_value("pake.log.source", entry.source.as_str()),
+            key_value("pake.log.target", entry.target.as_str()),
+            key_value("pake.log.seq", entry.seq),
+        ];
+        attributes.extend(entry.fields.iter().map(|(key, value)| key_value(key, value.clone())));
+        let time = (entry.timestamp.max(0) as u128 * 1_000_000).to_string();
+        json!({
+            "timeUnixNano": time,
+            "observedTimeUnixNano": time,
+            "severityNumber": severity,
+            "severityText": entry.level.as_str(),
+            "body": { "stringValue": entry.message },
+            "attributes": attributes,
+        })
+    }
+
+    fn span(span: &SpanRecord) -> Value {
+        let status = match &span.error {
+            None => json!({ "code": 1 }),
+            Some(message) => json!({ "code": 2, "message": message }),
+        };
+        json!({
+            "traceId": span.trace_id,
+            "spanId": span.span_id,
+            "name": span.name,
+            // SPAN_KIND_SERVER：命令由页面调用
+            "kind": 2,
+            "startTimeUnixNano": span.start.to_string(),
+            "endTimeUnixNano": span.end.to_string(),
+            "attributes": span.attributes,
+            "status": status,
+        })
+    }
+
+    async fn post(&self, path: &str, body: Value) -> Result<(), String> {
+        let response = self
+            .client
+            .post(format!("{}{}", self.endpoint, path))
+            .body(body.to_string())
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("HTTP {}", response.status()));
+        }
+        Ok(())
+    }
+
+    /// Sends and clears both batches. Failed batches are dropped rather than retried, so a
+    /// dead collector cannot make the queue grow.
+    async fn export(&mut self, logs: &mut Vec<LogEntry>, spans: &mut Vec<SpanRecord>) {
+        let mut result = Ok(());
+        if !logs.is_empty() {
+            let records: Vec<Value> = logs.drain(..).map(|entry| Self::log_record(&entry)).collect();
+            let body = json!({ "resourceLogs": [{
+                "resource": self.resource,
+                "scopeLogs": [{ "scope": self.scope, "logRecords": records }],
+            }] });
+            result = result.and(self.post(LOGS_PATH, body).await);
+        }
+        if !spans.is_empty() {
+            let records: Vec<Value> = spans.drain(..).map(|span| Self::span(&span)).collect();
+            let body = json!({ "resourceSpans": [{
+                "resource": self.resource,
+                "scopeSpans": [{ "scope": self.scope, "spans": records }],
+            }] });
+            result = result.and(self.post(TRACES_PATH, body).await);
+        }
+
+        match result {
+            // 只在状态变化时记录，避免导出失败的日志再被导出时刷屏
+            Ok(()) if self.failing => {
+                self.failing = false;
+                add_log_entry("INFO", &format!("已恢复导出到 OTLP 服务 {}", self.endpoint));
+            }
+            Err(e) if !self.failing => {
+                self.failing = true;
+                add_log_entry("WARN", &format!("导出到 OTLP 服务 {} 失败: {}", self.endpoint, e));
+            }
+            _ => {}
+        }
+        let dropped = DROPPED.swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            add_log_entry("WARN", &format!("OTLP 导出队列已满，丢弃了 {} 条记录", dropped));
+        }
+    }
+}
+
+/// `service.*`, `host.name` and `os.type`, plus every field of `app_info` (the `get_app_info`
+/// command) as `pake.app.*`.
+fn resource(app_info: &Value, identifier: &str) -> Value {
+    let info = app_info.as_object().cloned().unwrap_or_default();
+    let mut attributes = vec![
+        key_value("service.name", info.get("product_name").cloned().unwrap_or(Value::Null)),
+        key_value("service.version", info.get("version").cloned().unwrap_or(Value::Null)),
+        key_value("service.namespace", identifier),
+        key_value("service.instance.id", uuid::Uuid::new_v4().to_string()),
+        key_value("host.name", tauri_plugin_os::hostname()),
+        key_value("host.arch", tauri_plugin_os::arch()),
+        key_value("os.type", tauri_plugin_os::type_().to_string()),
+        key_value("os.version", tauri_plugin_os::version().to_string()),
+    ];
+    attributes.extend(info.iter().map(|(key, value)| key_value(&format!("pake.app.{}", key), value.clone())));
+    json!({ "attributes": attributes })
+}
+
+/// Collects records into batches of `batch_size`, sending partial ones every `export_interval`.
+async fn run(mut exporter: Exporter, config: OtlpConfig, mut receiver: Receiver<Telemetry>) {
+    let batch_size = config.batch_size.max(1);
+    let mut interval = tokio::time::interval(Duration::from_secs(config.export_interval.max(1)));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut logs = Vec::new();
+    let mut spans = Vec::new();
+
+    loop {
+        let flush = tokio::select! {
+            record = receiver.recv() => {
+                match record {
+                    Some(Telemetry::Log(entry)) => logs.push(entry),
+                    Some(Telemetry::Span(span)) => spans.push(span),
+                    None => break,
+                }
+                logs.len() >= batch_size || spans.len() >= batch_size
+            }
+            _ = interval.tick() => true,
+        };
+        if flush {
+            exporter.export(&mut logs, &mut spans).await;
+        }
+    }
+    exporter.export(&mut logs, &mut spans).await;
+}
+
+/// Starts exporting to `otlp.endpoint` when set, beginning with the entries already in the
+/// buffer so the startup logs are not missed.
+pub fn start_otlp(app: &AppHandle, pake_config: &PakeConfig) {
+    let config = &pake_config.otlp;
+    if !config.is_enabled() || OTLP.get().is_some() {
+        return;
+    }
+    let resource = resource(
+        &crate::util::app_info(app.config(), pake_config),
+        &app.config().identifier,
+    );
+    let exporter = match Exporter::new(config, resource) {
+        Ok(exporter) => exporter,
+        Err(e) => {
+            add_log_entry("ERROR", &format!("无法启用 OTLP 导出: {}", e));
+            return;
+        }
+    };
+    let min_level = LogLevel::from_str(&config.min_level).unwrap_or(LogLevel::Info);
+    let (sender, receiver) = channel(config.queue_size.max(1));
+    tauri::async_runtime::spawn(run(exporter, config.clone(), receiver));
+
+    // 补发并注册，见 push_log_entry
+    with_log_buffer(|buffer| {
+        if config.logs {
+            for entry in buffer.iter().filter(|entry| entry.level >= min_level) {
+                if sender.try_send(Telemetry::Log(entry.clone())).is_err() {
+                    DROPPED.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        let _ = OTLP.set(OtlpSink {
+            sender,
+            logs: config.logs,
+            traces: config.traces,
+            min_level,
+        });
+    });
+    add_log_entry("INFO", &format!("已启用 OTLP 导出: {}", config.endpoint));
+}
+
+/// Queues `entry` for export without blocking. A `push_log_entry` sink, see the rules there.
+pub fn forward_to_otlp(entry: &LogEntry) {
+    let Some(sink) = OTLP.get() else {
+        return;
+    };
+    if !sink.logs || entry.level < sink.min_level {
+        return;
+    }
+    if let Err(TrySendError::Full(_)) = sink.sender.try_send(Telemetry::Log(entry.clone())) {
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::invoke::{LogFields, LogSource};
+    use crate::app::stub_server::StubServer;
+
+    const WAIT: Duration = Duration::from_secs(5);
+
+    fn config(endpoint: &str, batch_size: usize, export_interval: u64) -> OtlpConfig {
+        OtlpConfig {
+            endpoint: format!("{}/", endpoint),
+            headers: [
+                ("Authorization".to_string(), "Bearer t0ken".to_string()),
+                ("X-Tenant".to_string(), "team-a".to_string()),
+            ]
+            .into_iter()
+            .collect(),
+            batch_size,
+            export_interval,
+            ..OtlpConfig::default()
+        }
+    }
+
+    fn test_resource() -> Value {
+        resource(
+            &json!({ "product_name": "Pake", "version": "1.2.3", "log_upload_enabled": false }),
+            "com.pake.test",
+        )
+    }
+
+    /// Runs the batching loop; dropping the sender flushes what is left.
+    async fn start(config: OtlpConfig) -> Sender<Telemetry> {
+        let exporter = Exporter::new(&config, test_resource()).unwrap();
+        let (sender, receiver) = channel(64);
+        tokio::spawn(run(exporter, config, receiver));
+        // 让第一次（立即触发的）定时导出先过去，之后的批次才确定
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        sender
+    }
+
+    fn entry(seq: u64) -> LogEntry {
+        let mut fields = LogFields::new();
+        fields.insert("status".to_string(), 404.into());
+        LogEntry {
+            seq,
+            timestamp: 1_714_560_000_123,
+            level: LogLevel::Warn,
+            source: LogSource::Network,
+            target: "fetch".to_string(),
+            message: format!("request {}", seq),
+            fields,
+        }
+    }
+
+    fn attribute<'a>(attributes: &'a Value, key: &str) -> &'a Value {
+        &attributes
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|attribute| attribute["key"] == key)
+            .unwrap_or_else(|| panic!("missing attribute {}", key))["value"]
+    }
+
+    fn log_records(body: &Value) -> &Vec<Value> {
+        body["resourceLogs"][0]["scopeLogs"][0]["logRecords"].as_array().unwrap()
+    }
+
+    /// The instance id is random per resource, so compare everything else.
+    fn test_resource_without_instance(body: &Value) -> Value {
+        let sent = &body["resourceLogs"][0]["resource"]["attributes"];
+        let mut expected = test_resource();
+        for item in expected["attributes"].as_array_mut().unwrap() {
+            if item["key"] == "service.instance.id" {
+                item["value"] = attribute(sent, "service.instance.id").clone();
+            }
+        }
+        expected
+    }
+
+    #[test]
+    fn resource_describes_service_and_app() {
+        let resource = test_resource();
+        let attributes = &resource["attributes"];
+        assert_eq!(attribute(attributes, "service.name"), &json!({ "stringValue": "Pake" }));
+        assert_eq!(attribute(attributes, "service.version"), &json!({ "stringValue": "1.2.3" }));
+        assert_eq!(
+            attribute(attributes, "service.namespace"),
+            &json!({ "stringValue": "com.pake.test" })
+        );
+        assert_eq!(
+            attribute(attributes, "pake.app.log_upload_enabled"),
+            &json!({ "boolValue": false })
+        );
+        for key in ["service.instance.id", "host.name", "host.arch", "os.type", "os.version"] {
+            attribute(attributes, key);
+        }
+    }
+
+    #[test]
+    fn any_value_encodes_json_types() {
+        assert_eq!(any_value(&json!(7)), json!({ "intValue": "7" }));
+        assert_eq!(any_value(&json!(0.5)), json!({ "doubleValue": 0.5 }));
+        assert_eq!(
+            any_value(&json!(["a", true])),
+            json!({ "arrayValue": { "values": [{ "stringValue": "a" }, { "boolValue": true }] } })
+        );
+        assert_eq!(
+            any_value(&json!({ "k": null })),
+            json!({ "kvlistValue": { "values": [{ "key": "k", "value": {} }] } })
+        );
+    }
+
+    #[tokio::test]
+    async fn batches_by_size_and_sends_logs_payload() {
+        let server = StubServer::ok().await;
+        let sender = start(config(&server.url, 2, 3600)).await;
+        for seq in 1..=5 {
+            sender.send(Telemetry::Log(entry(seq))).await.unwrap();
+        }
+
+        // 满两条立即发送，剩下的一条在队列关闭时发送
+        let requests = server.wait_for(2, WAIT).await;
+        assert_eq!(requests.len(), 2);
+        drop(sender);
+        let requests = server.wait_for(3, WAIT).await;
+        let sizes: Vec<usize> = requests.iter().map(|request| log_records(&request.json()).len()).collect();
+        assert_eq!(sizes, [2, 2, 1]);
+
+        let request = &requests[0];
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/v1/logs");
+        assert_eq!(request.header("content-type"), Some("application/json"));
+        assert_eq!(request.header("authorization"), Some("Bearer t0ken"));
+        assert_eq!(request.header("x-tenant"), Some("team-a"));
+
+        let body = request.json();
+        assert_eq!(body["resourceLogs"][0]["resource"], test_resource_without_instance(&body));
+        assert_eq!(
+            body["resourceLogs"][0]["scopeLogs"][0]["scope"],
+            json!({ "name": "pake", "version": env!("CARGO_PKG_VERSION") })
+        );
+        let record = &log_records(&body)[0];
+        assert_eq!(record["timeUnixNano"], "1714560000123000000");
+        assert_eq!(record["observedTimeUnixNano"], "1714560000123000000");
+        assert_eq!(record["severityNumber"], 13);
+        assert_eq!(record["severityText"], "WARN");
+        assert_eq!(record["body"], json!({ "stringValue": "request 1" }));
+        let attributes = &record["attributes"];
+        assert_eq!(attribute(attributes, "pake.log.source"), &json!({ "stringValue": "network" }));
+        assert_eq!(attribute(attributes, "pake.log.target"), &json!({ "stringValue": "fetch" }));
+        assert_eq!(attribute(attributes, "pake.log.seq"), &json!({ "intValue": "1" }));
+        assert_eq!(attribute(attributes, "status"), &json!({ "intValue": "404" }));
+    }
+
+    #[tokio::test]
+    async fn sends_partial_batch_after_interval() {
+        let server = StubServer::ok().await;
+        let sender = start(config(&server.url, 100, 1)).await;
+        sender.send(Telemetry::Log(entry(1))).await.unwrap();
+        sender.send(Telemetry::Log(entry(2))).await.unwrap();
+
+        let requests = server.wait_for(1, WAIT).await;
+        assert_eq!(log_records(&requests[0].json()).len(), 2);
+        // 队列仍然打开，说明是按时间间隔发送的
+        assert!(!sender.is_closed());
+    }
+
+    #[tokio::test]
+    async fn sends_traces_payload() {
+        let server = StubServer::ok().await;
+        let sender = start(config(&server.url, 100, 3600)).await;
+        for (span_id, error) in [("00000000000000a1", None), ("00000000000000a2", Some("磁盘已满"))] {
+            let span = SpanRecord {
+                trace_id: "0123456789abcdef0123456789abcdef".to_string(),
+                span_id: span_id.to_string(),
+                name: "download_file",
+                start: 1_714_560_000_123_000_000,
+                end: 1_714_560_000_456_000_000,
+                attributes: vec![key_value("pake.command", "download_file")],
+                error: error.map(str::to_string),
+            };
+            sender.send(Telemetry::Span(span)).await.unwrap();
+        }
+        drop(sender);
+
+        let requests = server.wait_for(1, WAIT).await;
+        let request = &requests[0];
+        assert_eq!(request.path, "/v1/traces");
+        assert_eq!(request.header("authorization"), Some("Bearer t0ken"));
+        let body = request.json();
+        let resource_spans = &body["resourceSpans"][0];
+        assert_eq!(
+            attribute(&resource_spans["resource"]["attributes"], "service.name"),
+            &json!({ "stringValue": "Pake" })
+        );
+        let spans = resource_spans["scopeSpans"][0]["spans"].as_array().unwrap();
+        assert_eq!(spans.len(), 2);
+        assert_eq!(
+            spans[0],
+            json!({
+                "traceId": "0123456789abcdef0123456789abcdef",
+                "spanId": "00000000000000a1",
+                "name": "download_file",
+                "kind": 2,
+                "startTimeUnixNano": "1714560000123000000",
+                "endTimeUnixNano": "1714560000456000000",
+                "attributes": [{ "key": "pake.command", "value": { "stringValue": "download_file" } }],
+                "status": { "code": 1 },
+            })
+        );
+        assert_eq!(spans[1]["status"], json!({ "code": 2, "message": "磁盘已满" }));
+    }
+
+    #[test]
+    fn rejects_invalid_headers() {
+        let mut config = config("http://127.0.0.1:4318", 1, 1);
+        config.headers.insert("bad header".to_string(), "x".to_string());
+        assert!(Exporter::new(&config, test_resource()).is_err());
+    }
+}
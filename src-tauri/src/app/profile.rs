@@ -0,0 +1,128 @@
+use crate::app::config::PakeConfig;
+use crate::app::invoke::add_log_entry;
+use crate::app::validate::validate_config;
+use crate::util::get_pake_config;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+use tauri_plugin_store::StoreExt;
+
+/// Store file remembering the profile picked from the tray. It lives in the app config dir
+/// because the data dir is cleared together with the webview cache on Linux.
+pub const PROFILE_STORE: &str = "pake-profile.json";
+const ACTIVE_PROFILE_KEY: &str = "active_profile";
+
+/// Tray menu ids for profiles are `profile:<name>`.
+pub const PROFILE_MENU_PREFIX: &str = "profile:";
+
+/// Profile the running windows were built with, if pake.json defines any.
+pub struct ActiveProfile {
+    pub name: Option<String>,
+    /// Chosen with `--profile`; the tray cannot switch away from it because a restart
+    /// would pass the same argument again.
+    pub pinned: bool,
+}
+
+/// `requested` (from `--profile`), then the remembered profile, then `default_profile`,
+/// then the first one listed. Errors when `requested` is not a known profile.
+pub fn initial_profile(
+    config: &PakeConfig,
+    identifier: &str,
+    requested: Option<&str>,
+) -> Result<Option<String>, String> {
+    if let Some(name) = requested {
+        if config.profile(name).is_none() {
+            let known: Vec<&str> = config.profiles.iter().map(|p| p.name.as_str()).collect();
+            return Err(format!(
+                "unknown profile \"{}\", available: [{}]",
+                name,
+                known.join(", ")
+            ));
+        }
+        return Ok(Some(name.to_string()));
+    }
+
+    if config.profiles.is_empty() {
+        return Ok(None);
+    }
+
+    Ok([stored_profile(identifier), Some(config.default_profile.clone())]
+        .into_iter()
+        .flatten()
+        .find(|name| config.profile(name).is_some())
+        .or_else(|| config.profiles.first().map(|profile| profile.name.clone())))
+}
+
+/// Reads the store file directly so the profile is known before the app is built,
+/// e.g. for `--print-config`.
+fn stored_profile(identifier: &str) -> Option<String> {
+    // Same location as `app.path().app_config_dir()`.
+    let path = dirs::config_dir()?.join(identifier).join(PROFILE_STORE);
+    let content = std::fs::read_to_string(path).ok()?;
+    let store: serde_json::Value = serde_json::from_str(&content).ok()?;
+    store[ACTIVE_PROFILE_KEY].as_str().map(str::to_string)
+}
+
+/// Remembers `name` and restarts the app with it. Proxy, user agent and data directory can
+/// only be set when a webview is created, so the windows have to be rebuilt from scratch.
+pub fn switch_profile(app: &AppHandle, name: &str) {
+    let active = app.state::<ActiveProfile>();
+    if active.pinned || active.name.as_deref() == Some(name) {
+        return;
+    }
+
+    let (mut config, _) = get_pake_config();
+    if config.profile(name).is_none() {
+        add_log_entry("ERROR", &format!("未知的环境配置: {}", name));
+        return;
+    }
+    config.apply_profile(name);
+
+    // Refuse to restart into a profile that would stop the app from starting.
+    let fatal: Vec<String> = validate_config(app, &mut config)
+        .into_iter()
+        .filter(|issue| !issue.recovered)
+        .map(|issue| format!("• {}", issue))
+        .collect();
+    if !fatal.is_empty() {
+        add_log_entry("ERROR", &format!("环境 {} 配置无效，未切换", name));
+        app.dialog()
+            .message(fatal.join("\n"))
+            .title(format!("无法切换到环境 {}", name))
+            .kind(MessageDialogKind::Error)
+            .show(|_| {});
+        return;
+    }
+
+    let store = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| e.to_string())
+        .and_then(|dir| app.store(dir.join(PROFILE_STORE)).map_err(|e| e.to_string()));
+    match store {
+        Ok(store) => {
+            store.set(ACTIVE_PROFILE_KEY, name);
+            if let Err(e) = store.save() {
+                add_log_entry("ERROR", &format!("保存环境配置失败: {}", e));
+            }
+        }
+        Err(e) => add_log_entry("ERROR", &format!("打开环境配置存储失败: {}", e)),
+    }
+
+    add_log_entry("INFO", &format!("切换到环境 {}，正在重启应用", name));
+    app.restart();
+}
+
+/// Stable 16-byte id for the macOS WebKit data store of a profile.
+#[cfg(target_os = "macos")]
+pub fn data_store_identifier(name: &str) -> [u8; 16] {
+    // FNV-1a, run twice with different offsets to fill 128 bits.
+    let fnv = |offset: u64| {
+        name.bytes().fold(offset, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+    };
+    let mut identifier = [0u8; 16];
+    identifier[..8].copy_from_slice(&fnv(0xcbf2_9ce4_8422_2325).to_be_bytes());
+    identifier[8..].copy_from_slice(&fnv(0x6c62_272e_07bb_0142).to_be_bytes());
+    identifier
+}
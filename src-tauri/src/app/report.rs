@@ -0,0 +1,326 @@
+use crate::app::bundle::BundleWriter;
+use crate::app::invoke::add_log_entry;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{command, AppHandle};
+use tauri_plugin_dialog::DialogExt;
+use tokio::sync::oneshot;
+
+pub const REPORT_FILE: &str = "report.json";
+const MAX_DESCRIPTION_CHARS: usize = 5000;
+const MAX_CONTACT_CHARS: usize = 200;
+const MAX_URL_CHARS: usize = 2048;
+const MAX_ATTACHMENTS: usize = 5;
+const MAX_ATTACHMENT_SIZE: u64 = 10 * 1024 * 1024;
+
+// 通过文件对话框选中的附件。页面只拿到 id，不能直接指定本地路径
+static PICKED_ATTACHMENTS: once_cell::sync::Lazy<Mutex<HashMap<String, PathBuf>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportCategory {
+    Bug,
+    Crash,
+    Performance,
+    Network,
+    Account,
+    Other,
+}
+
+/// "Report a problem" form sent by the page along with the logs.
+#[derive(Debug, Deserialize)]
+pub struct FeedbackReport {
+    pub description: String,
+    pub category: ReportCategory,
+    #[serde(default)]
+    pub contact: Option<String>,
+    /// Ids returned by [`pick_report_attachments`].
+    #[serde(default)]
+    pub attachments: Vec<String>,
+    /// Defaults to the URL of the calling window.
+    #[serde(default)]
+    pub page_url: Option<String>,
+}
+
+/// A file picked through the dialog, as shown to the page.
+#[derive(Debug, Clone, Serialize)]
+pub struct AttachmentInfo {
+    pub id: String,
+    pub name: String,
+    pub size: u64,
+}
+
+/// Validated report, written to the bundle as `report.json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportRecord {
+    pub report_id: String,
+    pub created_at: String,
+    pub category: ReportCategory,
+    pub description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contact: Option<String>,
+    pub page_url: Option<String>,
+    pub attachments: Vec<ReportAttachment>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportAttachment {
+    /// Path inside the bundle, e.g. `attachments/1-screenshot.png`.
+    pub name: String,
+    pub original_name: String,
+    pub size: u64,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+/// Short enough to read out over the phone, e.g. `20261018-7F3A9C21`.
+fn new_report_id() -> String {
+    let random = uuid::Uuid::new_v4().simple().to_string();
+    format!("{}-{}", Utc::now().format("%Y%m%d"), random[..8].to_uppercase())
+}
+
+fn file_name_of(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "attachment".to_string())
+}
+
+/// Keeps bundle member names portable across zip tools.
+fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' })
+        .collect()
+}
+
+fn attachment_size(path: &Path) -> Result<u64, String> {
+    let metadata = std::fs::metadata(path).map_err(|e| format!("无法读取附件 {:?}: {}", path, e))?;
+    if !metadata.is_file() {
+        return Err(format!("附件不是文件: {:?}", path));
+    }
+    if metadata.len() > MAX_ATTACHMENT_SIZE {
+        return Err(format!(
+            "附件 {} 超过 {} MB",
+            file_name_of(path),
+            MAX_ATTACHMENT_SIZE / 1024 / 1024
+        ));
+    }
+    Ok(metadata.len())
+}
+
+impl FeedbackReport {
+    /// Checks the form and claims its attachments; `window_url` is the URL of the calling window.
+    /// Attachments are only claimed when the whole report is valid.
+    pub fn validate(self, window_url: Option<String>) -> Result<ReportRecord, String> {
+        let description = self.description.trim().to_string();
+        if description.is_empty() {
+            return Err("请填写问题描述".to_string());
+        }
+        if description.chars().count() > MAX_DESCRIPTION_CHARS {
+            return Err(format!("问题描述不能超过 {} 个字符", MAX_DESCRIPTION_CHARS));
+        }
+
+        let contact = self
+            .contact
+            .map(|contact| contact.trim().to_string())
+            .filter(|contact| !contact.is_empty());
+        if contact.as_ref().is_some_and(|contact| contact.chars().count() > MAX_CONTACT_CHARS) {
+            return Err(format!("联系方式不能超过 {} 个字符", MAX_CONTACT_CHARS));
+        }
+
+        let page_url = match self.page_url.filter(|url| !url.is_empty()) {
+            Some(url) if url.len() > MAX_URL_CHARS => return Err("页面地址过长".to_string()),
+            Some(url) => Some(
+                url::Url::parse(&url)
+                    .map_err(|e| format!("无效的页面地址 {}: {}", url, e))?
+                    .to_string(),
+            ),
+            None => window_url,
+        };
+
+        if self.attachments.len() > MAX_ATTACHMENTS {
+            return Err(format!("附件不能超过 {} 个", MAX_ATTACHMENTS));
+        }
+        let mut attachments = Vec::with_capacity(self.attachments.len());
+        {
+            let mut picked = PICKED_ATTACHMENTS.lock().unwrap();
+            for (index, id) in self.attachments.iter().enumerate() {
+                if self.attachments[..index].contains(id) {
+                    return Err(format!("附件重复: {}", id));
+                }
+                let path = picked
+                    .get(id)
+                    .cloned()
+                    .ok_or_else(|| format!("附件已失效，请重新选择: {}", id))?;
+                let original_name = file_name_of(&path);
+                attachments.push(ReportAttachment {
+                    name: format!("attachments/{}-{}", index + 1, sanitize_file_name(&original_name)),
+                    original_name,
+                    size: attachment_size(&path)?,
+                    path,
+                });
+            }
+            // 全部检查通过后才认领，失败时页面可以改正后用同样的 id 重新提交
+            for id in &self.attachments {
+                picked.remove(id);
+            }
+        }
+
+        Ok(ReportRecord {
+            report_id: new_report_id(),
+            created_at: Utc::now().to_rfc3339(),
+            category: self.category,
+            description,
+            contact,
+            page_url,
+            attachments,
+        })
+    }
+}
+
+impl ReportRecord {
+    /// Adds `report.json` and the attachments to the bundle.
+    pub fn write_to<W: Write + Seek>(&self, bundle: &mut BundleWriter<W>) -> Result<(), String> {
+        let json = serde_json::to_vec_pretty(self).map_err(|e| e.to_string())?;
+        bundle.add(REPORT_FILE, |writer| writer.write_all(&json))?;
+        for attachment in &self.attachments {
+            bundle.add(&attachment.name, |writer| {
+                std::io::copy(&mut File::open(&attachment.path)?, writer).map(|_| ())
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Opens a native file picker for report attachments such as screenshots. The page gets
+/// back ids to pass in `report.attachments`, never the local paths themselves.
+#[command]
+pub async fn pick_report_attachments(app: AppHandle) -> Result<Vec<AttachmentInfo>, String> {
+    let (sender, receiver) = oneshot::channel();
+    app.dialog()
+        .file()
+        .set_title("选择附件")
+        .add_filter("图片", &["png", "jpg", "jpeg", "gif", "webp", "bmp"])
+        .add_filter("所有文件", &["*"])
+        .pick_files(move |paths| {
+            let _ = sender.send(paths);
+        });
+    let Some(paths) = receiver.await.map_err(|e| e.to_string())? else {
+        return Ok(Vec::new());
+    };
+
+    let mut picked = Vec::new();
+    for path in paths {
+        let path = path.into_path().map_err(|e| e.to_string())?;
+        let size = attachment_size(&path)?;
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        picked.push(AttachmentInfo {
+            id: id.clone(),
+            name: file_name_of(&path),
+            size,
+        });
+        PICKED_ATTACHMENTS.lock().unwrap().insert(id, path);
+    }
+    add_log_entry("INFO", &format!("已选择 {} 个反馈附件", picked.len()));
+    Ok(picked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pick(dir: &Path, name: &str, size: u64) -> String {
+        let path = dir.join(name);
+        File::create(&path).unwrap().set_len(size).unwrap();
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        PICKED_ATTACHMENTS.lock().unwrap().insert(id.clone(), path);
+        id
+    }
+
+    fn is_picked(id: &str) -> bool {
+        PICKED_ATTACHMENTS.lock().unwrap().contains_key(id)
+    }
+
+    fn report(description: &str, attachments: &[&String]) -> FeedbackReport {
+        FeedbackReport {
+            description: description.to_string(),
+            category: ReportCategory::Bug,
+            contact: None,
+            attachments: attachments.iter().map(|id| id.to_string()).collect(),
+            page_url: None,
+        }
+    }
+
+    #[test]
+    fn valid_report_claims_its_attachments() {
+        let dir = tempfile::tempdir().unwrap();
+        let screenshot = pick(dir.path(), "屏幕 截图.png", 3);
+        let trace = pick(dir.path(), "trace.har", 5);
+
+        let mut form = report("  白屏  ", &[&screenshot, &trace]);
+        form.contact = Some("   ".to_string());
+        let record = form.validate(Some("https://x.com/orders".to_string())).unwrap();
+
+        assert_eq!(record.description, "白屏");
+        assert_eq!(record.contact, None);
+        assert_eq!(record.page_url.as_deref(), Some("https://x.com/orders"));
+        let names: Vec<(&str, &str, u64)> = record
+            .attachments
+            .iter()
+            .map(|attachment| (attachment.name.as_str(), attachment.original_name.as_str(), attachment.size))
+            .collect();
+        assert_eq!(
+            names,
+            [("attachments/1-屏幕_截图.png", "屏幕 截图.png", 3), ("attachments/2-trace.har", "trace.har", 5)]
+        );
+        assert!(!is_picked(&screenshot) && !is_picked(&trace));
+
+        // 已认领的附件不能再用
+        let error = report("again", &[&screenshot]).validate(None).unwrap_err();
+        assert!(error.starts_with("附件已失效"), "{}", error);
+    }
+
+    #[test]
+    fn invalid_report_keeps_its_attachments() {
+        let dir = tempfile::tempdir().unwrap();
+        let kept = pick(dir.path(), "kept.png", 1);
+        let too_large = pick(dir.path(), "large.mov", MAX_ATTACHMENT_SIZE + 1);
+        let missing = "not-picked".to_string();
+        let many: Vec<String> = (0..=MAX_ATTACHMENTS).map(|_| kept.clone()).collect();
+
+        let mut long_contact = report("desc", &[&kept]);
+        long_contact.contact = Some("x".repeat(MAX_CONTACT_CHARS + 1));
+        let mut bad_url = report("desc", &[&kept]);
+        bad_url.page_url = Some("not a url".to_string());
+        let mut long_url = report("desc", &[&kept]);
+        long_url.page_url = Some(format!("https://x.com/{}", "a".repeat(MAX_URL_CHARS)));
+
+        let cases = [
+            (report(" ", &[&kept]), "请填写问题描述"),
+            (report(&"字".repeat(MAX_DESCRIPTION_CHARS + 1), &[&kept]), "问题描述不能超过"),
+            (long_contact, "联系方式不能超过"),
+            (bad_url, "无效的页面地址"),
+            (long_url, "页面地址过长"),
+            (report("desc", &many.iter().collect::<Vec<_>>()), "附件不能超过"),
+            (report("desc", &[&kept, &kept]), "附件重复"),
+            (report("desc", &[&kept, &missing]), "附件已失效"),
+            (report("desc", &[&kept, &too_large]), "附件 large.mov 超过"),
+        ];
+        for (form, expected) in cases {
+            let error = form.validate(None).unwrap_err();
+            assert!(error.starts_with(expected), "{} / {}", error, expected);
+            assert!(is_picked(&kept), "{}", expected);
+        }
+        assert!(is_picked(&too_large));
+
+        // 改正后用同样的 id 仍可提交
+        let record = report("desc", &[&kept]).validate(None).unwrap();
+        assert_eq!(record.attachments.len(), 1);
+        assert!(!is_picked(&kept));
+    }
+}
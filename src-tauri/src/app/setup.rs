@@ -1,8 +1,9 @@
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tauri::{
-    menu::{MenuBuilder, MenuItemBuilder},
+    menu::{CheckMenuItemBuilder, MenuBuilder, MenuItemBuilder, SubmenuBuilder},
     tray::TrayIconBuilder,
     AppHandle, Manager,
     image::Image,
@@ -10,6 +11,9 @@ use tauri::{
 use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut};
 use tauri_plugin_window_state::{AppHandleExt, StateFlags};
 use crate::app::config::PakeConfig;
+use crate::app::invoke::add_log_entry;
+use crate::app::log_export::export_diagnostics;
+use crate::app::profile::{switch_profile, ActiveProfile, PROFILE_MENU_PREFIX};
 
 pub fn set_system_tray(app: &AppHandle, show_system_tray: bool, pake_config: &PakeConfig) -> tauri::Result<()> {
     if !show_system_tray {
@@ -19,89 +23,81 @@ pub fn set_system_tray(app: &AppHandle, show_system_tray: bool, pake_config: &Pa
 
     let hide_app = MenuItemBuilder::with_id("hide_app", "Hide").build(app)?;
     let show_app = MenuItemBuilder::with_id("show_app", "Show").build(app)?;
+    let export_item = MenuItemBuilder::with_id("export_diagnostics", "Export diagnostics…").build(app)?;
     let quit = MenuItemBuilder::with_id("quit", "Quit").build(app)?;
 
-    let menu = MenuBuilder::new(app)
-        .items(&[&hide_app, &show_app, &quit])
+    let mut menu_builder = MenuBuilder::new(app);
+
+    // 环境切换子菜单，勾选当前环境
+    if !pake_config.profiles.is_empty() {
+        let (active_profile, pinned) = app
+            .try_state::<ActiveProfile>()
+            .map(|active| (active.name.clone(), active.pinned))
+            .unwrap_or_default();
+        let mut profile_menu = SubmenuBuilder::new(app, "Profile");
+        for profile in &pake_config.profiles {
+            let item = CheckMenuItemBuilder::with_id(
+                format!("{}{}", PROFILE_MENU_PREFIX, profile.name),
+                profile.display_name(),
+            )
+            .checked(active_profile.as_deref() == Some(profile.name.as_str()))
+            .enabled(!pinned)
+            .build(app)?;
+            profile_menu = profile_menu.item(&item);
+        }
+        menu_builder = menu_builder.item(&profile_menu.build()?).separator();
+    }
+
+    let menu = menu_builder
+        .items(&[&hide_app, &show_app])
+        .separator()
+        .item(&export_item)
+        .separator()
+        .item(&quit)
         .build()?;
 
     app.app_handle().remove_tray_by_id("pake-tray");
 
     // 尝试加载自定义托盘图标
-    let icon = if !pake_config.system_tray_path.is_empty() {
-        // 在macOS上，优先尝试ICNS格式的图标
-        let icon_name = std::path::Path::new(&pake_config.system_tray_path)
-            .file_stem()
-            .unwrap()
-            .to_str()
-            .unwrap();
-        
-        let possible_icon_paths = vec![
-            format!("icons/{}.icns", icon_name),  // ICNS格式优先
-            pake_config.system_tray_path.clone(), // 原始路径
-        ];
-        
-        let mut loaded_icon = None;
-        for icon_path_str in possible_icon_paths {
-            let possible_paths = vec![
-                // 打包后的应用资源目录
-                app.path().resource_dir().ok().map(|p| p.join(&icon_path_str)),
-                // 开发环境的项目目录
-                app.path().app_local_data_dir().ok().map(|p| p.parent().unwrap().parent().unwrap().join(&icon_path_str)),
-            ];
-            
-            for path_opt in possible_paths {
-                if let Some(icon_path) = path_opt {
-                    if icon_path.exists() {
-                        match Image::from_path(&icon_path) {
-                            Ok(icon) => {
-                                println!("Successfully loaded system tray icon from: {}", icon_path.display());
-                                loaded_icon = Some(icon);
-                                break;
-                            }
-                            Err(e) => {
-                                eprintln!("Failed to load system tray icon from {}: {}", icon_path.display(), e);
-                            }
-                        }
-                    } else {
-                        eprintln!("System tray icon path does not exist: {}", icon_path.display());
-                    }
-                }
+    let icon = tray_icon_candidates(app, &pake_config.system_tray_path)
+        .into_iter()
+        .find_map(|icon_path| match Image::from_path(&icon_path) {
+            Ok(icon) => {
+                println!("Successfully loaded system tray icon from: {}", icon_path.display());
+                Some(icon)
             }
-            
-            if loaded_icon.is_some() {
-                break;
+            Err(e) => {
+                eprintln!("Failed to load system tray icon from {}: {}", icon_path.display(), e);
+                None
             }
-        }
-        
-        loaded_icon.unwrap_or_else(|| {
-            eprintln!("Could not find system tray icon, using default");
-            app.default_window_icon().unwrap().clone()
         })
-    } else {
-        app.default_window_icon().unwrap().clone()
-    };
+        .unwrap_or_else(|| app.default_window_icon().unwrap().clone());
 
     let tray = TrayIconBuilder::new()
         .menu(&menu)
         .on_menu_event(move |app, event| match event.id().as_ref() {
             "hide_app" => {
-                if let Some(window) = app.get_webview_window("pake") {
+                for window in app.webview_windows().values() {
                     window.minimize().unwrap();
                 }
             }
             "show_app" => {
-                if let Some(window) = app.get_webview_window("pake") {
+                for window in app.webview_windows().values() {
                     window.show().unwrap();
                 }
             }
+            "export_diagnostics" => export_diagnostics(app),
             "quit" => {
                 app.save_window_state(StateFlags::all()).unwrap();
                 // 在退出前清理webview缓存
                 crate::clear_webview_data(app);
                 std::process::exit(0);
             }
-            _ => (),
+            id => {
+                if let Some(profile) = id.strip_prefix(PROFILE_MENU_PREFIX) {
+                    switch_profile(app, profile);
+                }
+            }
         })
         .icon(icon)
         .build(app)?;
@@ -110,13 +106,65 @@ pub fn set_system_tray(app: &AppHandle, show_system_tray: bool, pake_config: &Pa
     Ok(())
 }
 
-pub fn set_global_shortcut(app: &AppHandle, shortcut: String) -> tauri::Result<()> {
-    if shortcut.is_empty() {
+/// Existing files that may hold the tray icon for `system_tray_path`, in load order.
+pub fn tray_icon_candidates(app: &AppHandle, system_tray_path: &str) -> Vec<PathBuf> {
+    if system_tray_path.is_empty() {
+        return Vec::new();
+    }
+
+    // 在macOS上，优先尝试ICNS格式的图标
+    let icon_name = Path::new(system_tray_path)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default();
+    let possible_icon_paths = [
+        format!("icons/{}.icns", icon_name), // ICNS格式优先
+        system_tray_path.to_string(),        // 原始路径
+    ];
+
+    let base_dirs = [
+        // 打包后的应用资源目录
+        app.path().resource_dir().ok(),
+        // 开发环境的项目目录
+        app.path()
+            .app_local_data_dir()
+            .ok()
+            .and_then(|p| p.parent().and_then(Path::parent).map(Path::to_path_buf)),
+    ];
+
+    possible_icon_paths
+        .iter()
+        .flat_map(|icon_path| {
+            base_dirs
+                .iter()
+                .flatten()
+                .map(move |base_dir| base_dir.join(icon_path))
+        })
+        .filter(|icon_path| icon_path.exists())
+        .collect()
+}
+
+pub fn set_global_shortcut(app: &AppHandle, shortcuts: Vec<(String, String)>) -> tauri::Result<()> {
+    if shortcuts.is_empty() {
+        return Ok(());
+    }
+
+    // 通常已由 validate_config 清掉，这里仍然跳过无法解析的快捷键而不是 panic
+    let shortcut_hotkeys: Vec<(Shortcut, String)> = shortcuts
+        .into_iter()
+        .filter_map(|(label, shortcut)| match Shortcut::from_str(&shortcut) {
+            Ok(hotkey) => Some((hotkey, label)),
+            Err(e) => {
+                add_log_entry("WARN", &format!("忽略无法解析的快捷键 {} ({}): {}", shortcut, label, e));
+                None
+            }
+        })
+        .collect();
+    if shortcut_hotkeys.is_empty() {
         return Ok(());
     }
 
     let app_handle = app.clone();
-    let shortcut_hotkey = Shortcut::from_str(&shortcut).unwrap();
     let last_triggered = Arc::new(Mutex::new(Instant::now()));
 
     app_handle
@@ -124,6 +172,7 @@ pub fn set_global_shortcut(app: &AppHandle, shortcut: String) -> tauri::Result<(
             tauri_plugin_global_shortcut::Builder::new()
                 .with_handler({
                     let last_triggered = Arc::clone(&last_triggered);
+                    let shortcut_hotkeys = shortcut_hotkeys.clone();
                     move |app, event, _shortcut| {
                         let mut last_triggered = last_triggered.lock().unwrap();
                         if Instant::now().duration_since(*last_triggered)
@@ -133,15 +182,19 @@ pub fn set_global_shortcut(app: &AppHandle, shortcut: String) -> tauri::Result<(
                         }
                         *last_triggered = Instant::now();
 
-                        if shortcut_hotkey.eq(event) {
-                            if let Some(window) = app.get_webview_window("pake") {
-                                let is_visible = window.is_visible().unwrap();
-                                if is_visible {
-                                    window.hide().unwrap();
-                                } else {
-                                    window.show().unwrap();
-                                    window.set_focus().unwrap();
-                                }
+                        let Some((_, label)) =
+                            shortcut_hotkeys.iter().find(|(hotkey, _)| hotkey.eq(event))
+                        else {
+                            return;
+                        };
+
+                        if let Some(window) = app.get_webview_window(label) {
+                            let is_visible = window.is_visible().unwrap();
+                            if is_visible {
+                                window.hide().unwrap();
+                            } else {
+                                window.show().unwrap();
+                                window.set_focus().unwrap();
                             }
                         }
                     }
@@ -150,7 +203,11 @@ pub fn set_global_shortcut(app: &AppHandle, shortcut: String) -> tauri::Result<(
         )
         .expect("Failed to set global shortcut");
 
-    app.global_shortcut().register(shortcut_hotkey).unwrap();
+    for (hotkey, label) in shortcut_hotkeys {
+        if let Err(e) = app.global_shortcut().register(hotkey) {
+            add_log_entry("WARN", &format!("无法注册快捷键 ({}): {}", label, e));
+        }
+    }
 
     Ok(())
 }
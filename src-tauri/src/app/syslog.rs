@@ -0,0 +1,579 @@
+use crate::app::config::{SyslogConfig, SyslogProtocol};
+use crate::app::invoke::{add_log_entry, with_log_buffer, LogEntry, LogLevel};
+use chrono::{Local, SecondsFormat, TimeZone};
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use std::fs::File;
+use std::io::{BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+// 单个 UDP 报文的上限，超出的消息会被截断
+const UDP_MAX_MESSAGE: usize = 8192;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+static SYSLOG: once_cell::sync::OnceCell<SyslogSink> = once_cell::sync::OnceCell::new();
+
+// 队列已满时丢弃的条目数，发送恢复后记录一条警告
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+
+struct SyslogSink {
+    sender: SyncSender<LogEntry>,
+    min_level: LogLevel,
+}
+
+/// Fields shared by every message, already reduced to what RFC 5424 allows.
+struct SyslogHeader {
+    facility: u8,
+    hostname: String,
+    app_name: String,
+    procid: String,
+    identifier: String,
+    version: String,
+}
+
+/// Header fields are printable ASCII without spaces; `-` stands for an empty value.
+fn header_field(value: &str, max_len: usize) -> String {
+    let field: String = value
+        .chars()
+        .map(|c| if c.is_ascii_graphic() { c } else { '_' })
+        .take(max_len)
+        .collect();
+    if field.is_empty() {
+        "-".to_string()
+    } else {
+        field
+    }
+}
+
+/// Escapes a structured data parameter value.
+fn param_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace(']', "\\]")
+}
+
+impl SyslogHeader {
+    fn collect(app: &AppHandle, facility: u8) -> Self {
+        let package = app.package_info();
+        let product_name = app.config().product_name.clone().unwrap_or_else(|| package.name.clone());
+        Self {
+            facility,
+            hostname: header_field(&tauri_plugin_os::hostname(), 255),
+            app_name: header_field(&product_name, 48),
+            procid: std::process::id().to_string(),
+            identifier: param_value(&app.config().identifier),
+            version: param_value(&package.version.to_string()),
+        }
+    }
+
+    /// `<PRI>1 TIMESTAMP HOSTNAME APP-NAME PROCID MSGID [SD] MSG`, with the source as MSGID
+    /// and the message laid out like a log file line without time and level.
+    fn format(&self, entry: &LogEntry) -> String {
+        let severity = match entry.level {
+            LogLevel::Error => 3,
+            LogLevel::Warn => 4,
+            LogLevel::Info => 6,
+            LogLevel::Debug | LogLevel::Trace => 7,
+        };
+        let timestamp = Local
+            .timestamp_millis_opt(entry.timestamp)
+            .single()
+            .map(|time| time.to_rfc3339_opts(SecondsFormat::Millis, false))
+            .unwrap_or_else(|| "-".to_string());
+        let mut message = format!(
+            "<{}>1 {} {} {} {} {} [origin software=\"{}\" swVersion=\"{}\"][meta sequenceId=\"{}\"] \u{feff}{}: {}",
+            self.facility as u32 * 8 + severity,
+            timestamp,
+            self.hostname,
+            self.app_name,
+            self.procid,
+            entry.source.as_str(),
+            self.identifier,
+            self.version,
+            entry.seq % 2_147_483_647 + 1,
+            entry.target,
+            entry.message
+        );
+        for (key, value) in &entry.fields {
+            match value.as_str() {
+                Some(value) => message.push_str(&format!(" {}={}", key, value)),
+                None => message.push_str(&format!(" {}={}", key, value)),
+            }
+        }
+        message
+    }
+}
+
+enum Connection {
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+    Tls(Box<StreamOwned<ClientConnection, TcpStream>>),
+}
+
+impl Connection {
+    fn send(&mut self, message: &str) -> std::io::Result<()> {
+        match self {
+            Connection::Udp(socket) => {
+                let mut end = message.len().min(UDP_MAX_MESSAGE);
+                while !message.is_char_boundary(end) {
+                    end -= 1;
+                }
+                socket.send(&message.as_bytes()[..end]).map(|_| ())
+            }
+            // 按 RFC 6587 / RFC 5425 使用长度前缀分帧
+            Connection::Tcp(stream) => write!(stream, "{} {}", message.len(), message),
+            Connection::Tls(stream) => {
+                write!(stream, "{} {}", message.len(), message)?;
+                stream.flush()
+            }
+        }
+    }
+}
+
+/// Where and how to connect; resolved again on every reconnect.
+struct Collector {
+    address: String,
+    protocol: SyslogProtocol,
+    tls: Option<(Arc<ClientConfig>, ServerName<'static>)>,
+}
+
+impl Collector {
+    fn new(app: &AppHandle, config: &SyslogConfig) -> Result<Self, String> {
+        let tls = match config.protocol {
+            SyslogProtocol::Tls => {
+                let host = config
+                    .address
+                    .rsplit_once(':')
+                    .map_or(config.address.as_str(), |(host, _)| host)
+                    .trim_start_matches('[')
+                    .trim_end_matches(']');
+                let server_name = ServerName::try_from(host.to_string())
+                    .map_err(|e| format!("无效的主机名 {}: {}", host, e))?;
+                let ca_file = match config.ca_file.as_str() {
+                    "" => None,
+                    ca_file => Some(resolve_config_path(app, ca_file)?),
+                };
+                Some((tls_config(ca_file.as_deref())?, server_name))
+            }
+            _ => None,
+        };
+        Ok(Self {
+            address: config.address.clone(),
+            protocol: config.protocol,
+            tls,
+        })
+    }
+
+    fn connect_tcp(&self) -> std::io::Result<TcpStream> {
+        let mut last_error = None;
+        for addr in self.address.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+                Ok(stream) => {
+                    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+                    stream.set_nodelay(true)?;
+                    return Ok(stream);
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| std::io::Error::other("地址无法解析")))
+    }
+
+    fn connect(&self) -> std::io::Result<Connection> {
+        match self.protocol {
+            SyslogProtocol::Udp => {
+                let addr = self
+                    .address
+                    .to_socket_addrs()?
+                    .next()
+                    .ok_or_else(|| std::io::Error::other("地址无法解析"))?;
+                let socket = UdpSocket::bind(if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" })?;
+                socket.connect(addr)?;
+                Ok(Connection::Udp(socket))
+            }
+            SyslogProtocol::Tcp => Ok(Connection::Tcp(self.connect_tcp()?)),
+            SyslogProtocol::Tls => {
+                let (config, server_name) = self.tls.clone().expect("TLS config for tls protocol");
+                let connection = ClientConnection::new(config, server_name).map_err(std::io::Error::other)?;
+                Ok(Connection::Tls(Box::new(StreamOwned::new(connection, self.connect_tcp()?))))
+            }
+        }
+    }
+}
+
+/// Relative paths are resolved against the app config dir.
+fn resolve_config_path(app: &AppHandle, path: &str) -> Result<PathBuf, String> {
+    let path = Path::new(path);
+    if path.is_absolute() {
+        return Ok(path.to_path_buf());
+    }
+    Ok(app.path().app_config_dir().map_err(|e| e.to_string())?.join(path))
+}
+
+/// Trusts the certificates in `ca_file`, or the Mozilla root store without one.
+fn tls_config(ca_file: Option<&Path>) -> Result<Arc<ClientConfig>, String> {
+    let mut roots = RootCertStore::empty();
+    if let Some(path) = ca_file {
+        let file = File::open(path).map_err(|e| format!("无法读取 CA 证书 {:?}: {}", path, e))?;
+        for cert in rustls_pemfile::certs(&mut BufReader::new(file)) {
+            let cert = cert.map_err(|e| format!("无法解析 CA 证书 {:?}: {}", path, e))?;
+            roots.add(cert).map_err(|e| format!("无法解析 CA 证书 {:?}: {}", path, e))?;
+        }
+        if roots.is_empty() {
+            return Err(format!("CA 证书文件中没有证书: {:?}", path));
+        }
+    } else {
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    }
+    let config = ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(Arc::new(config))
+}
+
+/// Sends queued entries one by one, reconnecting with backoff. An entry that cannot be sent
+/// is retried until it goes through, while newer ones pile up in the bounded queue.
+fn run(collector: Collector, header: SyslogHeader, receiver: Receiver<LogEntry>) {
+    let mut connection: Option<Connection> = None;
+    let mut retry_delay = INITIAL_RETRY_DELAY;
+    let mut failing = false;
+
+    while let Ok(entry) = receiver.recv() {
+        let message = header.format(&entry);
+        loop {
+            let result = match connection.as_mut() {
+                Some(connection) => connection.send(&message),
+                None => collector.connect().and_then(|new| connection.insert(new).send(&message)),
+            };
+            match result {
+                Ok(()) => {
+                    if failing {
+                        failing = false;
+                        retry_delay = INITIAL_RETRY_DELAY;
+                        add_log_entry("INFO", &format!("已恢复连接 syslog 服务器 {}", collector.address));
+                    }
+                    break;
+                }
+                Err(e) => {
+                    connection = None;
+                    // 只在开始失败时记录一次，避免重试时刷屏
+                    if !failing {
+                        failing = true;
+                        add_log_entry(
+                            "WARN",
+                            &format!("无法发送日志到 syslog 服务器 {}: {}，将在后台重试", collector.address, e),
+                        );
+                    }
+                    std::thread::sleep(retry_delay);
+                    retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+                }
+            }
+        }
+
+        let dropped = DROPPED.swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            add_log_entry("WARN", &format!("syslog 发送队列已满，丢弃了 {} 条日志", dropped));
+        }
+    }
+}
+
+/// Starts forwarding to `config.address` when set, beginning with the entries already in
+/// the buffer so the startup logs are not missed.
+pub fn start_syslog(app: &AppHandle, config: &SyslogConfig) {
+    if !config.is_enabled() || SYSLOG.get().is_some() {
+        return;
+    }
+    let collector = match Collector::new(app, config) {
+        Ok(collector) => collector,
+        Err(e) => {
+            add_log_entry("ERROR", &format!("无法启用 syslog 转发: {}", e));
+            return;
+        }
+    };
+    let header = SyslogHeader::collect(app, config.facility);
+    let min_level = LogLevel::from_str(&config.min_level).unwrap_or(LogLevel::Info);
+    let (sender, receiver) = sync_channel(config.queue_size.max(1));
+
+    let description = format!("{} ({:?})", collector.address, config.protocol);
+    if let Err(e) = std::thread::Builder::new()
+        .name("pake-syslog".to_string())
+        .spawn(move || run(collector, header, receiver))
+    {
+        add_log_entry("ERROR", &format!("无法启动 syslog 线程: {}", e));
+        return;
+    }
+
+    // 补发并注册，见 push_log_entry
+    with_log_buffer(|buffer| {
+        for entry in buffer.iter().filter(|entry| entry.level >= min_level) {
+            if sender.try_send(entry.clone()).is_err() {
+                DROPPED.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        let _ = SYSLOG.set(SyslogSink { sender, min_level });
+    });
+    add_log_entry("INFO", &format!("已启用 syslog 转发: {}", description));
+}
+
+/// Queues `entry` for the collector without blocking. A `push_log_entry` sink, see the rules
+/// there.
+pub fn forward_to_syslog(entry: &LogEntry) {
+    let Some(sink) = SYSLOG.get() else {
+        return;
+    };
+    if entry.level < sink.min_level {
+        return;
+    }
+    if let Err(TrySendError::Full(_)) = sink.sender.try_send(entry.clone()) {
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::invoke::{LogFields, LogSource};
+    use rustls::{ServerConfig, ServerConnection};
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::time::Instant;
+
+    // 测试证书由 openssl 生成：自签名 CA 签发的 localhost / 127.0.0.1 证书，有效期 100 年
+    const CA_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/app/testdata/syslog-ca.pem");
+    const CERT_PEM: &[u8] = include_bytes!("testdata/syslog-localhost.pem");
+    const KEY_PEM: &[u8] = include_bytes!("testdata/syslog-localhost.key");
+
+    fn header(facility: u8) -> SyslogHeader {
+        SyslogHeader {
+            facility,
+            hostname: "build-host".to_string(),
+            app_name: "Pake".to_string(),
+            procid: "42".to_string(),
+            identifier: "com.pake.test".to_string(),
+            version: "1.0.0".to_string(),
+        }
+    }
+
+    fn entry(seq: u64, level: LogLevel, message: &str) -> LogEntry {
+        LogEntry {
+            seq,
+            timestamp: 1_714_560_000_123,
+            level,
+            source: LogSource::Webview,
+            target: "console".to_string(),
+            message: message.to_string(),
+            fields: LogFields::new(),
+        }
+    }
+
+    fn collector(address: String, protocol: SyslogProtocol) -> Collector {
+        Collector {
+            address,
+            protocol,
+            tls: None,
+        }
+    }
+
+    /// Reads one `LEN SP MSG` frame.
+    fn read_frame(reader: &mut impl Read) -> String {
+        let mut length = String::new();
+        let mut byte = [0u8];
+        loop {
+            reader.read_exact(&mut byte).unwrap();
+            if byte[0] == b' ' {
+                break;
+            }
+            length.push(byte[0] as char);
+        }
+        let mut message = vec![0; length.parse().unwrap()];
+        reader.read_exact(&mut message).unwrap();
+        String::from_utf8(message).unwrap()
+    }
+
+    #[test]
+    fn formats_rfc5424_header() {
+        let mut entry = entry(7, LogLevel::Info, "hello world");
+        entry.fields.insert("url".to_string(), "https://example.com/".into());
+        entry.fields.insert("status".to_string(), 200.into());
+        let timestamp = Local
+            .timestamp_millis_opt(entry.timestamp)
+            .unwrap()
+            .to_rfc3339_opts(SecondsFormat::Millis, false);
+
+        assert_eq!(
+            header(16).format(&entry),
+            format!(
+                "<134>1 {} build-host Pake 42 webview [origin software=\"com.pake.test\" swVersion=\"1.0.0\"][meta sequenceId=\"8\"] \u{feff}console: hello world status=200 url=https://example.com/",
+                timestamp
+            )
+        );
+    }
+
+    #[test]
+    fn pri_combines_facility_and_severity() {
+        let pri = |facility, level| {
+            let message = header(facility).format(&entry(0, level, "x"));
+            message[1..message.find('>').unwrap()].to_string()
+        };
+        assert_eq!(pri(0, LogLevel::Error), "3");
+        assert_eq!(pri(1, LogLevel::Warn), "12");
+        assert_eq!(pri(16, LogLevel::Info), "134");
+        assert_eq!(pri(23, LogLevel::Debug), "191");
+        assert_eq!(pri(23, LogLevel::Trace), "191");
+    }
+
+    #[test]
+    fn sequence_id_stays_in_range() {
+        let header = header(1);
+        let sequence_id = |seq| {
+            let message = header.format(&entry(seq, LogLevel::Info, "x"));
+            let start = message.find("sequenceId=\"").unwrap() + "sequenceId=\"".len();
+            message[start..].split('"').next().unwrap().to_string()
+        };
+        assert_eq!(sequence_id(0), "1");
+        assert_eq!(sequence_id(2_147_483_646), "2147483647");
+        assert_eq!(sequence_id(2_147_483_647), "1");
+    }
+
+    #[test]
+    fn header_fields_are_printable_ascii() {
+        assert_eq!(header_field("my host", 255), "my_host");
+        assert_eq!(header_field("应用", 48), "__");
+        assert_eq!(header_field("", 48), "-");
+        assert_eq!(header_field(&"a".repeat(60), 48), "a".repeat(48));
+        assert_eq!(param_value(r#"a"b]c\d"#), r#"a\"b\]c\\d"#);
+    }
+
+    #[test]
+    fn udp_truncates_at_limit_on_char_boundary() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut connection = collector(server.local_addr().unwrap().to_string(), SyslogProtocol::Udp)
+            .connect()
+            .unwrap();
+        let mut buffer = vec![0; 65536];
+
+        connection.send("short").unwrap();
+        let received = server.recv(&mut buffer).unwrap();
+        assert_eq!(&buffer[..received], b"short");
+
+        connection.send(&"a".repeat(10_000)).unwrap();
+        assert_eq!(server.recv(&mut buffer).unwrap(), UDP_MAX_MESSAGE);
+
+        // 8192 落在两字节字符中间时退回到字符边界
+        let message = format!("a{}", "é".repeat(5000));
+        connection.send(&message).unwrap();
+        let received = server.recv(&mut buffer).unwrap();
+        assert_eq!(received, UDP_MAX_MESSAGE - 1);
+        assert_eq!(&buffer[..received], &message.as_bytes()[..received]);
+    }
+
+    #[test]
+    fn tcp_uses_octet_counting() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut connection = collector(listener.local_addr().unwrap().to_string(), SyslogProtocol::Tcp)
+            .connect()
+            .unwrap();
+        connection.send("héllo").unwrap();
+        connection.send("second message").unwrap();
+
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut raw = vec![0; "6 héllo14 second message".len()];
+        stream.read_exact(&mut raw).unwrap();
+        assert_eq!(String::from_utf8(raw).unwrap(), "6 héllo14 second message");
+    }
+
+    #[test]
+    fn tls_uses_octet_counting() {
+        let certs = rustls_pemfile::certs(&mut &CERT_PEM[..]).collect::<Result<Vec<_>, _>>().unwrap();
+        let key = rustls_pemfile::private_key(&mut &KEY_PEM[..]).unwrap().unwrap();
+        let server_config = ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            let connection = ServerConnection::new(Arc::new(server_config)).unwrap();
+            let mut stream = StreamOwned::new(connection, stream);
+            [read_frame(&mut stream), read_frame(&mut stream)]
+        });
+
+        let collector = Collector {
+            address,
+            protocol: SyslogProtocol::Tls,
+            tls: Some((
+                tls_config(Some(Path::new(CA_PATH))).unwrap(),
+                ServerName::try_from("localhost").unwrap(),
+            )),
+        };
+        let mut connection = collector.connect().unwrap();
+        connection.send("héllo").unwrap();
+        connection.send("second message").unwrap();
+
+        assert_eq!(server.join().unwrap(), ["héllo", "second message"]);
+    }
+
+    #[test]
+    fn tls_config_requires_certificates() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        assert!(tls_config(Some(file.path())).unwrap_err().contains("没有证书"));
+    }
+
+    #[test]
+    fn reconnects_after_listener_drops() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let (sender, receiver) = sync_channel(64);
+        let worker = collector(address.to_string(), SyslogProtocol::Tcp);
+        std::thread::spawn(move || run(worker, header(1), receiver));
+
+        sender.send(entry(1, LogLevel::Info, "before restart")).unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        assert!(read_frame(&mut stream).ends_with("console: before restart"));
+        drop(stream);
+        drop(listener);
+
+        // 断开后的第一条可能还能写入旧连接，持续发送直到重新连上
+        let listener = TcpListener::bind(address).unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let feeder = sender.clone();
+        std::thread::spawn(move || {
+            for seq in 2..300 {
+                if feeder.send(entry(seq, LogLevel::Info, "after restart")).is_err() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+        });
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut stream = loop {
+            match listener.accept() {
+                Ok((stream, _)) => break stream,
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock && Instant::now() < deadline => {
+                    std::thread::sleep(Duration::from_millis(20));
+                }
+                Err(e) => panic!("collector did not reconnect: {}", e),
+            }
+        };
+        stream.set_nonblocking(false).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        assert!(read_frame(&mut stream).ends_with("console: after restart"));
+    }
+}
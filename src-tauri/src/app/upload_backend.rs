@@ -0,0 +1,504 @@
+use crate::app::config::{LogUploadConfig, UploadBackendKind};
+use futures_util::StreamExt;
+use reqwest::header::CONTENT_LENGTH;
+use reqwest::multipart::{Form, Part};
+use reqwest::{Body, Client, Method, RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_util::io::ReaderStream;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Called with the number of bytes sent so far.
+pub type ProgressFn = Arc<dyn Fn(u64) + Send + Sync>;
+
+/// A finished bundle (or one part of it) on disk, waiting to be sent.
+pub struct UploadArtifact {
+    pub file_name: String,
+    pub content_type: &'static str,
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+impl UploadArtifact {
+    pub fn from_file(file_name: String, path: PathBuf) -> Result<Self, String> {
+        let size = std::fs::metadata(&path)
+            .map_err(|e| format!("无法读取 {:?}: {}", path, e))?
+            .len();
+        Ok(Self {
+            // 拆分后的分片不是完整的 zip
+            content_type: if file_name.ends_with(".zip") {
+                "application/zip"
+            } else {
+                "application/octet-stream"
+            },
+            file_name,
+            path,
+            size,
+        })
+    }
+
+    /// Streams the file in chunks, reporting progress as each chunk is handed to the request.
+    async fn body(&self, progress: ProgressFn) -> Result<Body, String> {
+        let file = tokio::fs::File::open(&self.path)
+            .await
+            .map_err(|e| format!("无法打开 {:?}: {}", self.path, e))?;
+        let mut sent = 0u64;
+        let stream = ReaderStream::with_capacity(file, CHUNK_SIZE).map(move |chunk| {
+            if let Ok(bytes) = &chunk {
+                sent += bytes.len() as u64;
+                progress(sent);
+            }
+            chunk
+        });
+        Ok(Body::wrap_stream(stream))
+    }
+}
+
+/// Same shape for every backend, returned to the page.
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadResult {
+    /// Object key, URL or path the bundle ended up at.
+    pub remote_name: String,
+    pub size: u64,
+    pub elapsed_ms: u64,
+}
+
+/// 请求预签名URL的请求体结构
+#[derive(Serialize)]
+struct PresignedUrlRequest<'a> {
+    file_name: &'a str,
+    content_type: &'a str,
+    expiration: u32,
+    path: &'a str,
+}
+
+/// 预签名URL的响应体结构
+#[derive(Deserialize)]
+struct PresignedUrlResponse {
+    presigned_url: String,
+}
+
+/// `(header name, value)` added to every request sent to the configured endpoint.
+type AuthHeader = Option<(String, String)>;
+
+pub enum UploadBackend {
+    Presigned {
+        endpoint: String,
+        auth: AuthHeader,
+        path_prefix: String,
+        expiration: u32,
+    },
+    Http {
+        /// `{file_name}` is replaced with the bundle name.
+        url: String,
+        method: Method,
+        auth: AuthHeader,
+    },
+    Multipart {
+        url: String,
+        field: String,
+        auth: AuthHeader,
+    },
+    WebDav {
+        collection: String,
+        auth: AuthHeader,
+    },
+    Local {
+        directory: PathBuf,
+    },
+}
+
+impl UploadBackend {
+    /// Relative `directory` values are resolved against `base_dir`.
+    pub fn from_config(
+        config: &LogUploadConfig,
+        api_key: Option<String>,
+        base_dir: &Path,
+    ) -> Result<Self, String> {
+        let auth = api_key.map(|key| (config.auth_header.clone(), key));
+        Ok(match config.backend {
+            UploadBackendKind::Presigned => UploadBackend::Presigned {
+                endpoint: config.endpoint.clone(),
+                auth,
+                path_prefix: config.path_prefix.clone(),
+                expiration: config.expiration,
+            },
+            UploadBackendKind::Http => UploadBackend::Http {
+                url: config.endpoint.clone(),
+                method: match Method::from_str(&config.method.to_ascii_uppercase()) {
+                    Ok(method) if method == Method::PUT || method == Method::POST => method,
+                    _ => return Err(format!("log_upload.method 只能是 PUT 或 POST: {}", config.method)),
+                },
+                auth,
+            },
+            UploadBackendKind::Multipart => UploadBackend::Multipart {
+                url: config.endpoint.clone(),
+                field: config.form_field.clone(),
+                auth,
+            },
+            UploadBackendKind::Webdav => UploadBackend::WebDav {
+                collection: config.endpoint.clone(),
+                auth,
+            },
+            UploadBackendKind::Local => UploadBackend::Local {
+                directory: base_dir.join(&config.directory),
+            },
+        })
+    }
+
+    pub async fn upload(
+        &self,
+        client: &Client,
+        artifact: &UploadArtifact,
+        progress: ProgressFn,
+    ) -> Result<UploadResult, String> {
+        let started = Instant::now();
+        let size = artifact.size;
+
+        let remote_name = match self {
+            UploadBackend::Presigned {
+                endpoint,
+                auth,
+                path_prefix,
+                expiration,
+            } => {
+                let res = with_auth(client.post(endpoint), auth)
+                    .json(&PresignedUrlRequest {
+                        file_name: &artifact.file_name,
+                        content_type: artifact.content_type,
+                        expiration: *expiration,
+                        path: path_prefix,
+                    })
+                    .send()
+                    .await
+                    .map_err(|e| format!("Failed to request presigned URL: {}", e))?;
+                let presigned_info = ensure_success(res, "Failed to get presigned URL")
+                    .await?
+                    .json::<PresignedUrlResponse>()
+                    .await
+                    .map_err(|e| format!("Failed to parse presigned URL response: {}", e))?;
+
+                // 预签名地址自带签名，不再附加密钥
+                let res = client
+                    .put(&presigned_info.presigned_url)
+                    .header("Content-Type", artifact.content_type)
+                    .header(CONTENT_LENGTH, size)
+                    .body(artifact.body(progress).await?)
+                    .send()
+                    .await
+                    .map_err(|e| format!("Failed to upload zip file: {}", e))?;
+                ensure_success(res, "Failed to upload logs").await?;
+                format!("{}{}", path_prefix, artifact.file_name)
+            }
+            UploadBackend::Http { url, method, auth } => {
+                let url = url.replace("{file_name}", &artifact.file_name);
+                let res = with_auth(client.request(method.clone(), &url), auth)
+                    .header("Content-Type", artifact.content_type)
+                    .header(CONTENT_LENGTH, size)
+                    .body(artifact.body(progress).await?)
+                    .send()
+                    .await
+                    .map_err(|e| format!("Failed to upload logs: {}", e))?;
+                ensure_success(res, "Failed to upload logs").await?;
+                url
+            }
+            UploadBackend::Multipart { url, field, auth } => {
+                let part = Part::stream_with_length(artifact.body(progress).await?, size)
+                    .file_name(artifact.file_name.clone())
+                    .mime_str(artifact.content_type)
+                    .map_err(|e| e.to_string())?;
+                let res = with_auth(client.post(url), auth)
+                    .multipart(Form::new().part(field.clone(), part))
+                    .send()
+                    .await
+                    .map_err(|e| format!("Failed to upload logs: {}", e))?;
+                ensure_success(res, "Failed to upload logs").await?;
+                artifact.file_name.clone()
+            }
+            UploadBackend::WebDav { collection, auth } => {
+                let url = format!("{}/{}", collection.trim_end_matches('/'), artifact.file_name);
+                let res = with_auth(client.put(&url), auth)
+                    .header("Content-Type", artifact.content_type)
+                    .header(CONTENT_LENGTH, size)
+                    .body(artifact.body(progress).await?)
+                    .send()
+                    .await
+                    .map_err(|e| format!("Failed to upload logs: {}", e))?;
+                ensure_success(res, "Failed to upload logs").await?;
+                url
+            }
+            UploadBackend::Local { directory } => {
+                let path = directory.join(&artifact.file_name);
+                tokio::fs::create_dir_all(directory)
+                    .await
+                    .map_err(|e| format!("无法创建目录 {:?}: {}", directory, e))?;
+                copy_with_progress(&artifact.path, &path, progress)
+                    .await
+                    .map_err(|e| format!("无法写入 {:?}: {}", path, e))?;
+                path.to_string_lossy().into_owned()
+            }
+        };
+
+        Ok(UploadResult {
+            remote_name,
+            size,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        })
+    }
+}
+
+async fn copy_with_progress(from: &Path, to: &Path, progress: ProgressFn) -> std::io::Result<()> {
+    let mut reader = tokio::fs::File::open(from).await?;
+    let mut writer = tokio::fs::File::create(to).await?;
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let mut copied = 0u64;
+    loop {
+        let read = reader.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..read]).await?;
+        copied += read as u64;
+        progress(copied);
+    }
+    writer.flush().await
+}
+
+fn with_auth(request: RequestBuilder, auth: &AuthHeader) -> RequestBuilder {
+    match auth {
+        Some((name, value)) => request.header(name.as_str(), value.as_str()),
+        None => request,
+    }
+}
+
+async fn ensure_success(res: Response, context: &str) -> Result<Response, String> {
+    let status = res.status();
+    if status.is_success() {
+        return Ok(res);
+    }
+    let error_body = res.text().await.unwrap_or_default();
+    Err(format!("{}: {} - {}", context, status, error_body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::stub_server::{Recorded, StubServer};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    /// Larger than one chunk so the body is streamed in several pieces.
+    fn content() -> Vec<u8> {
+        (0..200_000).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn artifact(dir: &Path, file_name: &str) -> UploadArtifact {
+        let path = dir.join(file_name);
+        std::fs::write(&path, content()).unwrap();
+        UploadArtifact::from_file(file_name.to_string(), path).unwrap()
+    }
+
+    fn auth() -> AuthHeader {
+        Some(("X-Api-Key".to_string(), "secret".to_string()))
+    }
+
+    async fn upload(backend: &UploadBackend, artifact: &UploadArtifact) -> Result<UploadResult, String> {
+        let sent = Arc::new(AtomicU64::new(0));
+        let reported = sent.clone();
+        let progress: ProgressFn = Arc::new(move |bytes| reported.store(bytes, Ordering::SeqCst));
+        let result = backend.upload(&Client::new(), artifact, progress).await;
+        if result.is_ok() {
+            assert_eq!(sent.load(Ordering::SeqCst), artifact.size);
+        }
+        result
+    }
+
+    fn assert_streamed(request: &Recorded, method: &str, path: &str, content_type: &str) {
+        assert_eq!(request.method, method);
+        assert_eq!(request.path, path);
+        assert_eq!(request.header("content-type"), Some(content_type));
+        assert_eq!(request.body, content());
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|window| window == needle)
+    }
+
+    #[tokio::test]
+    async fn presigned_requests_url_then_puts_without_key() {
+        let server = StubServer::start(|request| match request.path.as_str() {
+            "/presign" => (
+                200,
+                serde_json::json!({
+                    "presigned_url": format!("http://{}/bucket/object?sig=1", request.header("host").unwrap())
+                })
+                .to_string(),
+            ),
+            _ => (200, String::new()),
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let artifact = artifact(dir.path(), "bundle.zip");
+        let backend = UploadBackend::Presigned {
+            endpoint: format!("{}/presign", server.url),
+            auth: auth(),
+            path_prefix: "logs/".to_string(),
+            expiration: 600,
+        };
+
+        let result = upload(&backend, &artifact).await.unwrap();
+        assert_eq!(result.remote_name, "logs/bundle.zip");
+        assert_eq!(result.size, artifact.size);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].header("x-api-key"), Some("secret"));
+        assert_eq!(
+            requests[0].json(),
+            serde_json::json!({
+                "file_name": "bundle.zip",
+                "content_type": "application/zip",
+                "expiration": 600,
+                "path": "logs/",
+            })
+        );
+        assert_streamed(&requests[1], "PUT", "/bucket/object?sig=1", "application/zip");
+        assert_eq!(requests[1].header("x-api-key"), None);
+    }
+
+    #[tokio::test]
+    async fn http_sends_raw_body_to_templated_url() {
+        for method in [Method::PUT, Method::POST] {
+            let server = StubServer::ok().await;
+            let dir = tempfile::tempdir().unwrap();
+            let artifact = artifact(dir.path(), "bundle.zip.001");
+            let backend = UploadBackend::Http {
+                url: format!("{}/upload/{{file_name}}?v=1", server.url),
+                method: method.clone(),
+                auth: auth(),
+            };
+
+            let result = upload(&backend, &artifact).await.unwrap();
+            assert_eq!(result.remote_name, format!("{}/upload/bundle.zip.001?v=1", server.url));
+            assert_eq!(result.size, artifact.size);
+
+            let requests = server.requests();
+            assert_eq!(requests.len(), 1);
+            assert_streamed(
+                &requests[0],
+                method.as_str(),
+                "/upload/bundle.zip.001?v=1",
+                "application/octet-stream",
+            );
+            assert_eq!(requests[0].header("x-api-key"), Some("secret"));
+        }
+    }
+
+    #[tokio::test]
+    async fn multipart_posts_file_field() {
+        let server = StubServer::ok().await;
+        let dir = tempfile::tempdir().unwrap();
+        let artifact = artifact(dir.path(), "bundle.zip");
+        let backend = UploadBackend::Multipart {
+            url: format!("{}/form", server.url),
+            field: "logs".to_string(),
+            auth: auth(),
+        };
+
+        let result = upload(&backend, &artifact).await.unwrap();
+        assert_eq!(result.remote_name, "bundle.zip");
+        assert_eq!(result.size, artifact.size);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        let request = &requests[0];
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/form");
+        assert_eq!(request.header("x-api-key"), Some("secret"));
+        assert!(request
+            .header("content-type")
+            .unwrap()
+            .starts_with("multipart/form-data; boundary="));
+        assert!(contains(
+            &request.body,
+            b"Content-Disposition: form-data; name=\"logs\"; filename=\"bundle.zip\""
+        ));
+        assert!(contains(&request.body, b"Content-Type: application/zip"));
+        assert!(contains(&request.body, &content()));
+    }
+
+    #[tokio::test]
+    async fn webdav_puts_into_collection() {
+        let server = StubServer::ok().await;
+        let dir = tempfile::tempdir().unwrap();
+        let artifact = artifact(dir.path(), "bundle.zip");
+        let backend = UploadBackend::WebDav {
+            collection: format!("{}/dav/logs/", server.url),
+            auth: auth(),
+        };
+
+        let result = upload(&backend, &artifact).await.unwrap();
+        assert_eq!(result.remote_name, format!("{}/dav/logs/bundle.zip", server.url));
+        assert_eq!(result.size, artifact.size);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_streamed(&requests[0], "PUT", "/dav/logs/bundle.zip", "application/zip");
+        assert_eq!(requests[0].header("x-api-key"), Some("secret"));
+    }
+
+    #[tokio::test]
+    async fn local_copies_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let artifact = artifact(dir.path(), "bundle.zip");
+        let directory = dir.path().join("uploads").join("nested");
+        let backend = UploadBackend::Local {
+            directory: directory.clone(),
+        };
+
+        let result = upload(&backend, &artifact).await.unwrap();
+        let copied = directory.join("bundle.zip");
+        assert_eq!(result.remote_name, copied.to_string_lossy());
+        assert_eq!(result.size, artifact.size);
+        assert_eq!(std::fs::read(&copied).unwrap(), content());
+        // 原文件保留，由调用方决定何时删除
+        assert!(artifact.path.exists());
+    }
+
+    #[tokio::test]
+    async fn reports_error_status_and_body() {
+        let server = StubServer::start(|_| (503, "try later".to_string())).await;
+        let dir = tempfile::tempdir().unwrap();
+        let artifact = artifact(dir.path(), "bundle.zip");
+        let backend = UploadBackend::WebDav {
+            collection: server.url.clone(),
+            auth: None,
+        };
+
+        let error = upload(&backend, &artifact).await.unwrap_err();
+        assert!(error.contains("503"), "{}", error);
+        assert!(error.contains("try later"), "{}", error);
+    }
+
+    #[test]
+    fn http_method_must_be_put_or_post() {
+        let config = LogUploadConfig {
+            backend: UploadBackendKind::Http,
+            method: "get".to_string(),
+            ..LogUploadConfig::default()
+        };
+        assert!(UploadBackend::from_config(&config, None, Path::new("/")).is_err());
+
+        let config = LogUploadConfig {
+            method: "post".to_string(),
+            ..config
+        };
+        let backend = UploadBackend::from_config(&config, None, Path::new("/")).unwrap();
+        assert!(matches!(backend, UploadBackend::Http { method, .. } if method == Method::POST));
+    }
+}
@@ -0,0 +1,340 @@
+use crate::app::config::PakeConfig;
+use crate::app::invoke::{add_log_entry, send_notification, NotificationParams};
+use crate::app::log_upload::configured_backend;
+use crate::app::upload_backend::{UploadArtifact, UploadResult};
+use crate::util::get_main_window;
+use chrono::Local;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{command, AppHandle, Manager};
+use tokio::sync::Notify;
+
+// 唤醒后台重试任务，例如新加入队列或用户手动重试
+static QUEUE_WAKEUP: once_cell::sync::Lazy<Notify> = once_cell::sync::Lazy::new(Notify::new);
+
+const PENDING_DIR: &str = "pending-uploads";
+const FIRST_RETRY_DELAY_SECS: i64 = 30;
+const MAX_RETRY_DELAY_SECS: i64 = 60 * 60;
+
+/// Sidecar `<id>.json` next to `<id>.zip` in the pending-uploads directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingUpload {
+    pub id: String,
+    pub file_name: String,
+    pub size: u64,
+    /// Unix milliseconds.
+    pub created_at: i64,
+    pub attempts: u32,
+    pub last_error: String,
+    /// Unix milliseconds.
+    pub next_attempt_at: i64,
+}
+
+/// 30s after the first failure, doubling up to an hour.
+fn retry_delay_ms(attempts: u32) -> i64 {
+    let delay = FIRST_RETRY_DELAY_SECS.saturating_mul(1 << attempts.saturating_sub(1).min(16));
+    delay.min(MAX_RETRY_DELAY_SECS) * 1000
+}
+
+/// Lives in the log dir, which survives the webview data cleanup on exit.
+fn pending_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_log_dir()
+        .map(|dir| dir.join(PENDING_DIR))
+        .map_err(|e| e.to_string())
+}
+
+fn now_ms() -> i64 {
+    Local::now().timestamp_millis()
+}
+
+/// The id doubles as the file stem, so only accept what [`enqueue_upload`] generates.
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && !id.starts_with('.')
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+fn save_metadata(dir: &Path, pending: &PendingUpload) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(pending).map_err(|e| e.to_string())?;
+    std::fs::write(dir.join(format!("{}.json", pending.id)), json).map_err(|e| e.to_string())
+}
+
+fn load_pending(dir: &Path) -> Vec<PendingUpload> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut pending: Vec<PendingUpload> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| {
+            let content = std::fs::read_to_string(&path).ok()?;
+            serde_json::from_str::<PendingUpload>(&content).ok()
+        })
+        .filter(|pending| dir.join(format!("{}.zip", pending.id)).exists())
+        .collect();
+    pending.sort_by_key(|pending| pending.created_at);
+    pending
+}
+
+fn remove_pending(dir: &Path, id: &str) {
+    for extension in ["zip", "json"] {
+        let _ = std::fs::remove_file(dir.join(format!("{}.{}", id, extension)));
+    }
+}
+
+/// Keeps a bundle whose upload failed so the background task can retry it.
+pub fn enqueue_upload(app: &AppHandle, artifact: &UploadArtifact, error: &str) -> Result<PendingUpload, String> {
+    let pending = store_pending(&pending_dir(app)?, artifact, error)?;
+    add_log_entry("WARN", &format!("日志上传失败，已加入待上传队列: {}", pending.id));
+    QUEUE_WAKEUP.notify_one();
+    Ok(pending)
+}
+
+/// Copies the bundle into `dir` under a free id and writes its sidecar.
+fn store_pending(dir: &Path, artifact: &UploadArtifact, error: &str) -> Result<PendingUpload, String> {
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+
+    // 分片 name.zip.001 对应的 id 为 name.001
+    let stem = artifact.file_name.replace(".zip", "");
+    let stem = stem.as_str();
+    let mut id = stem.to_string();
+    let mut suffix = 1;
+    while dir.join(format!("{}.zip", id)).exists() {
+        id = format!("{}-{}", stem, suffix);
+        suffix += 1;
+    }
+
+    std::fs::copy(&artifact.path, dir.join(format!("{}.zip", id))).map_err(|e| e.to_string())?;
+    let now = now_ms();
+    let pending = PendingUpload {
+        id,
+        file_name: artifact.file_name.clone(),
+        size: artifact.size,
+        created_at: now,
+        attempts: 1,
+        last_error: error.to_string(),
+        next_attempt_at: now + retry_delay_ms(1),
+    };
+    save_metadata(dir, &pending)?;
+    Ok(pending)
+}
+
+async fn attempt_upload(app: &AppHandle, client: &Client, dir: &Path, mut pending: PendingUpload) {
+    let result: Result<UploadResult, String> = async {
+        let config = app.state::<PakeConfig>();
+        if !config.log_upload.is_enabled() {
+            return Err("日志上传未配置".to_string());
+        }
+        let backend = configured_backend(app, &config.log_upload)?;
+        let artifact =
+            UploadArtifact::from_file(pending.file_name.clone(), dir.join(format!("{}.zip", pending.id)))?;
+        // 后台重试不推送进度
+        backend.upload(client, &artifact, Arc::new(|_| {})).await
+    }
+    .await;
+
+    match result {
+        Ok(result) => {
+            remove_pending(dir, &pending.id);
+            add_log_entry(
+                "INFO",
+                &format!("待上传日志已上传: {} -> {}", pending.id, result.remote_name),
+            );
+            if let Some(window) = get_main_window(app) {
+                let _ = send_notification(
+                    app.clone(),
+                    window,
+                    NotificationParams::new(
+                        "日志已上传",
+                        &format!("之前失败的日志 {} 已上传成功", pending.file_name),
+                    ),
+                );
+            }
+        }
+        Err(e) => {
+            pending.attempts += 1;
+            pending.last_error = e.clone();
+            pending.next_attempt_at = now_ms() + retry_delay_ms(pending.attempts);
+            add_log_entry(
+                "WARN",
+                &format!("待上传日志重试失败 (第 {} 次): {} - {}", pending.attempts, pending.id, e),
+            );
+            if let Err(e) = save_metadata(dir, &pending) {
+                add_log_entry("ERROR", &format!("保存待上传日志状态失败: {}", e));
+            }
+        }
+    }
+}
+
+/// Retries due bundles, then sleeps until the next one is due or the queue is woken up.
+/// Bundles left over from a previous run are picked up immediately.
+pub fn start_upload_queue(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let Ok(dir) = pending_dir(&app) else {
+            return;
+        };
+        let client = Client::new();
+        let mut first_run = true;
+
+        loop {
+            let now = now_ms();
+            for pending in load_pending(&dir) {
+                if first_run || pending.next_attempt_at <= now {
+                    attempt_upload(&app, &client, &dir, pending).await;
+                }
+            }
+            first_run = false;
+
+            let wait = load_pending(&dir)
+                .iter()
+                .map(|pending| pending.next_attempt_at - now_ms())
+                .min()
+                .map(|ms| Duration::from_millis(ms.max(1000) as u64))
+                .unwrap_or(Duration::from_secs(MAX_RETRY_DELAY_SECS as u64));
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = QUEUE_WAKEUP.notified() => {}
+            }
+        }
+    });
+}
+
+#[command]
+pub fn list_pending_uploads(app: AppHandle) -> Result<Vec<PendingUpload>, String> {
+    Ok(load_pending(&pending_dir(&app)?))
+}
+
+/// Retries one bundle, or all of them when `id` is omitted, right away.
+#[command]
+pub fn retry_pending_upload(app: AppHandle, id: Option<String>) -> Result<(), String> {
+    let dir = pending_dir(&app)?;
+    let mut found = false;
+    for mut pending in load_pending(&dir) {
+        if id.as_deref().map_or(true, |id| id == pending.id) {
+            pending.next_attempt_at = now_ms();
+            save_metadata(&dir, &pending)?;
+            found = true;
+        }
+    }
+    if let (Some(id), false) = (&id, found) {
+        return Err(format!("找不到待上传日志: {}", id));
+    }
+    QUEUE_WAKEUP.notify_one();
+    Ok(())
+}
+
+#[command]
+pub fn discard_pending_upload(app: AppHandle, id: String) -> Result<(), String> {
+    if !is_valid_id(&id) {
+        return Err(format!("无效的待上传日志: {}", id));
+    }
+    remove_pending(&pending_dir(&app)?, &id);
+    add_log_entry("INFO", &format!("已丢弃待上传日志: {}", id));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_retry_after_thirty_seconds() {
+        assert_eq!(retry_delay_ms(0), 30_000);
+        assert_eq!(retry_delay_ms(1), 30_000);
+    }
+
+    #[test]
+    fn doubles_each_attempt() {
+        let delays: Vec<i64> = (1..=7).map(retry_delay_ms).collect();
+        assert_eq!(delays, [30_000, 60_000, 120_000, 240_000, 480_000, 960_000, 1_920_000]);
+    }
+
+    #[test]
+    fn capped_at_one_hour() {
+        assert_eq!(retry_delay_ms(8), 3_600_000);
+        assert_eq!(retry_delay_ms(9), 3_600_000);
+    }
+
+    #[test]
+    fn large_attempt_counts_do_not_overflow() {
+        for attempts in [17, 31, 32, 64, 1000, u32::MAX] {
+            assert_eq!(retry_delay_ms(attempts), 3_600_000);
+        }
+    }
+
+    fn artifact(dir: &Path, file_name: &str, content: &[u8]) -> UploadArtifact {
+        let path = dir.join(file_name);
+        std::fs::write(&path, content).unwrap();
+        UploadArtifact::from_file(file_name.to_string(), path).unwrap()
+    }
+
+    #[test]
+    fn stores_and_loads_pending_uploads() {
+        let source = tempfile::tempdir().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let queue = dir.path().join(PENDING_DIR);
+
+        let first = store_pending(&queue, &artifact(source.path(), "logs.zip", b"first"), "timeout").unwrap();
+        let second = store_pending(&queue, &artifact(source.path(), "logs.zip", b"second"), "500").unwrap();
+        let part = store_pending(&queue, &artifact(source.path(), "logs.zip.001", b"part"), "500").unwrap();
+        assert_eq!((first.id.as_str(), second.id.as_str(), part.id.as_str()), ("logs", "logs-1", "logs.001"));
+        assert_eq!(std::fs::read(queue.join("logs-1.zip")).unwrap(), b"second");
+        assert_eq!(part.file_name, "logs.zip.001");
+        assert_eq!(first.size, 5);
+        assert_eq!(first.attempts, 1);
+        assert_eq!(first.last_error, "timeout");
+        assert_eq!(first.next_attempt_at - first.created_at, retry_delay_ms(1));
+
+        let loaded = load_pending(&queue);
+        assert_eq!(loaded.len(), 3);
+        let loaded_first = loaded.iter().find(|pending| pending.id == "logs").unwrap();
+        assert_eq!(loaded_first.file_name, "logs.zip");
+        assert_eq!(loaded_first.next_attempt_at, first.next_attempt_at);
+        assert!(loaded.windows(2).all(|pair| pair[0].created_at <= pair[1].created_at));
+    }
+
+    #[test]
+    fn load_skips_corrupt_and_orphaned_entries() {
+        let source = tempfile::tempdir().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let queue = dir.path();
+        store_pending(queue, &artifact(source.path(), "good.zip", b"zip"), "500").unwrap();
+
+        std::fs::write(queue.join("corrupt.json"), "{ not json").unwrap();
+        std::fs::write(queue.join("corrupt.zip"), "zip").unwrap();
+        // 元数据还在但压缩包已经没了
+        let orphan = store_pending(queue, &artifact(source.path(), "orphan.zip", b"zip"), "500").unwrap();
+        std::fs::remove_file(queue.join(format!("{}.zip", orphan.id))).unwrap();
+        std::fs::write(queue.join("notes.txt"), "ignored").unwrap();
+
+        let ids: Vec<String> = load_pending(queue).into_iter().map(|pending| pending.id).collect();
+        assert_eq!(ids, ["good"]);
+        assert!(load_pending(&queue.join("missing")).is_empty());
+    }
+
+    #[test]
+    fn discarding_removes_bundle_and_sidecar() {
+        let source = tempfile::tempdir().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let queue = dir.path();
+        let kept = store_pending(queue, &artifact(source.path(), "kept.zip", b"zip"), "500").unwrap();
+        let dropped = store_pending(queue, &artifact(source.path(), "dropped.zip", b"zip"), "500").unwrap();
+
+        remove_pending(queue, &dropped.id);
+        assert!(!queue.join("dropped.zip").exists());
+        assert!(!queue.join("dropped.json").exists());
+        let ids: Vec<String> = load_pending(queue).into_iter().map(|pending| pending.id).collect();
+        assert_eq!(ids, [kept.id]);
+
+        for id in ["", "../kept", ".hidden", "a/b", "a\\b"] {
+            assert!(!is_valid_id(id), "{}", id);
+        }
+        assert!(is_valid_id("logs-1") && is_valid_id("logs.001"));
+    }
+}
@@ -0,0 +1,670 @@
+use crate::app::config::{
+    PakeConfig, UploadBackendKind, DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH,
+};
+use crate::app::invoke::{add_log_entry, LogLevel};
+use crate::app::log_level::LOG_SOURCES;
+use crate::app::redact::check_redaction_pattern;
+use crate::app::setup::tray_icon_candidates;
+use crate::util::get_embedded_config;
+use reqwest::header::{HeaderName, HeaderValue};
+use std::fmt;
+use std::str::FromStr;
+use tauri::{AppHandle, Url};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+use tauri_plugin_global_shortcut::Shortcut;
+
+/// A problem found in the effective `PakeConfig`, addressed by its field path.
+#[derive(Debug)]
+pub struct ConfigIssue {
+    /// Field path such as `windows[0].url`.
+    pub field: String,
+    pub problem: ConfigProblem,
+    /// Whether a default was substituted so the app can still start.
+    pub recovered: bool,
+}
+
+#[derive(Debug)]
+pub enum ConfigProblem {
+    Parse(String),
+    InvalidUrl { value: String, reason: String },
+    UnknownUrlType(String),
+    InvalidShortcut { value: String, reason: String },
+    MissingTrayIcon(String),
+    InvalidSize(f64),
+    InvalidScheme(String),
+    BundledSecret,
+    InvalidRecipient { value: String, reason: String },
+    InvalidPattern { value: String, reason: String },
+    InvalidLogLevel(String),
+    UnknownLogSource(String),
+    InvalidAddress(String),
+    InvalidFacility(u8),
+    InvalidHeader { name: String, reason: String },
+    WindowBuild(String),
+    NoWindows,
+    MissingProductName,
+    DataDir(String),
+}
+
+impl ConfigIssue {
+    pub fn parse(error: String) -> Self {
+        Self {
+            field: "pake.json".to_string(),
+            problem: ConfigProblem::Parse(error),
+            recovered: false,
+        }
+    }
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigProblem::Parse(e) => write!(f, "无法解析配置: {}", e),
+            ConfigProblem::InvalidUrl { value, reason } => {
+                write!(f, "无效的地址 \"{}\": {}", value, reason)
+            }
+            ConfigProblem::UnknownUrlType(value) => {
+                write!(f, "未知的 url_type \"{}\"，只能是 web 或 local", value)
+            }
+            ConfigProblem::InvalidShortcut { value, reason } => {
+                write!(f, "无法解析快捷键 \"{}\": {}", value, reason)
+            }
+            ConfigProblem::MissingTrayIcon(path) => write!(f, "找不到托盘图标 \"{}\"", path),
+            ConfigProblem::InvalidSize(value) => write!(f, "窗口尺寸必须大于 0，当前为 {}", value),
+            ConfigProblem::BundledSecret => write!(
+                f,
+                "密钥写在内置的 pake.json 中，会随安装包分发，请改用环境变量、密钥文件或 pake.override.json"
+            ),
+            ConfigProblem::InvalidScheme(value) => {
+                write!(f, "无效的链接协议 \"{}\"，只能包含字母、数字、+ - .，且不能是 http / https / file", value)
+            }
+            ConfigProblem::InvalidRecipient { value, reason } => {
+                write!(f, "无效的加密公钥 \"{}\": {}，已停用日志上传", value, reason)
+            }
+            ConfigProblem::InvalidPattern { value, reason } => {
+                write!(f, "无法解析正则表达式 \"{}\": {}", value, reason)
+            }
+            ConfigProblem::InvalidLogLevel(value) => {
+                write!(f, "未知的日志级别 \"{}\"，只能是 trace / debug / info / warn / error", value)
+            }
+            ConfigProblem::InvalidAddress(value) => {
+                write!(f, "无效的地址 \"{}\"，应为 主机:端口，已停用 syslog 转发", value)
+            }
+            ConfigProblem::InvalidHeader { name, reason } => {
+                write!(f, "无效的请求头 \"{}\": {}，已忽略", name, reason)
+            }
+            ConfigProblem::WindowBuild(reason) => write!(f, "无法创建窗口: {}", reason),
+            ConfigProblem::NoWindows => write!(f, "至少需要配置一个窗口"),
+            ConfigProblem::MissingProductName => write!(f, "tauri.conf.json 中缺少 productName"),
+            ConfigProblem::DataDir(reason) => write!(f, "无法准备数据目录: {}", reason),
+            ConfigProblem::InvalidFacility(value) => write!(f, "syslog facility 只能是 0-23，当前为 {}", value),
+            ConfigProblem::UnknownLogSource(value) => {
+                write!(f, "未知的日志来源 \"{}\"，只能是 {}", value, LOG_SOURCES.join(" / "))
+            }
+        }
+    }
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.problem)?;
+        if self.recovered {
+            write!(f, "（已使用默认值）")?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks every field that would otherwise panic later, replacing recoverable values with
+/// defaults in place. Problems that leave nothing sensible to start are returned unrecovered.
+pub fn validate_config(app: &AppHandle, config: &mut PakeConfig) -> Vec<ConfigIssue> {
+    let bundled_secret = get_embedded_config()
+        .0
+        .is_ok_and(|embedded| !embedded.log_upload.api_key.is_empty());
+    check_config(
+        config,
+        |path| !tray_icon_candidates(app, path).is_empty(),
+        bundled_secret,
+    )
+}
+
+/// [`validate_config`] without the app: `tray_icon_exists` looks up `system_tray_path` and
+/// `bundled_secret` tells whether the embedded pake.json carries an API key.
+fn check_config(
+    config: &mut PakeConfig,
+    tray_icon_exists: impl Fn(&str) -> bool,
+    bundled_secret: bool,
+) -> Vec<ConfigIssue> {
+    let mut issues = Vec::new();
+
+    if config.windows.is_empty() {
+        issues.push(ConfigIssue {
+            field: "windows".to_string(),
+            problem: ConfigProblem::NoWindows,
+            recovered: false,
+        });
+    }
+
+    for (index, window) in config.windows.iter_mut().enumerate() {
+        let field = |name: &str| format!("windows[{}].{}", index, name);
+
+        if window.url_type != "web" && window.url_type != "local" {
+            issues.push(ConfigIssue {
+                field: field("url_type"),
+                problem: ConfigProblem::UnknownUrlType(window.url_type.clone()),
+                recovered: true,
+            });
+            window.url_type = "web".to_string();
+        }
+
+        let url_error = if window.url_type == "web" {
+            check_url(&window.url, &["http", "https"]).err()
+        } else if window.url.trim().is_empty() {
+            Some("本地文件路径不能为空".to_string())
+        } else {
+            None
+        };
+        if let Some(reason) = url_error {
+            issues.push(ConfigIssue {
+                field: field("url"),
+                problem: ConfigProblem::InvalidUrl {
+                    value: window.url.clone(),
+                    reason,
+                },
+                recovered: false,
+            });
+        }
+
+        for (name, value, default) in [
+            ("width", &mut window.width, DEFAULT_WINDOW_WIDTH),
+            ("height", &mut window.height, DEFAULT_WINDOW_HEIGHT),
+        ] {
+            if !value.is_finite() || *value <= 0.0 {
+                issues.push(ConfigIssue {
+                    field: field(name),
+                    problem: ConfigProblem::InvalidSize(*value),
+                    recovered: true,
+                });
+                *value = default;
+            }
+        }
+
+        if !window.activation_shortcut.is_empty() {
+            if let Err(e) = Shortcut::from_str(&window.activation_shortcut) {
+                issues.push(ConfigIssue {
+                    field: field("activation_shortcut"),
+                    problem: ConfigProblem::InvalidShortcut {
+                        value: window.activation_shortcut.clone(),
+                        reason: e.to_string(),
+                    },
+                    recovered: true,
+                });
+                window.activation_shortcut.clear();
+            }
+        }
+    }
+
+    if !config.proxy_url.is_empty() {
+        if let Err(reason) = check_url(&config.proxy_url, &["http", "socks5"]) {
+            issues.push(ConfigIssue {
+                field: "proxy_url".to_string(),
+                problem: ConfigProblem::InvalidUrl {
+                    value: config.proxy_url.clone(),
+                    reason,
+                },
+                recovered: true,
+            });
+            config.proxy_url.clear();
+        }
+    }
+
+    if config.show_system_tray()
+        && !config.system_tray_path.is_empty()
+        && !tray_icon_exists(&config.system_tray_path)
+    {
+        issues.push(ConfigIssue {
+            field: "system_tray_path".to_string(),
+            problem: ConfigProblem::MissingTrayIcon(config.system_tray_path.clone()),
+            recovered: true,
+        });
+        config.system_tray_path.clear();
+    }
+
+    // A broken deep link config only disables deep links.
+    let deep_link = &mut config.deep_link;
+    if !deep_link.scheme.is_empty() && !is_valid_scheme(&deep_link.scheme) {
+        issues.push(ConfigIssue {
+            field: "deep_link.scheme".to_string(),
+            problem: ConfigProblem::InvalidScheme(deep_link.scheme.clone()),
+            recovered: true,
+        });
+        deep_link.scheme.clear();
+    }
+    if !deep_link.base_url.is_empty() {
+        if let Err(reason) = check_url(&deep_link.base_url, &["http", "https"]) {
+            issues.push(ConfigIssue {
+                field: "deep_link.base_url".to_string(),
+                problem: ConfigProblem::InvalidUrl {
+                    value: deep_link.base_url.clone(),
+                    reason,
+                },
+                recovered: true,
+            });
+            deep_link.scheme.clear();
+        }
+    }
+
+    let log_upload = &mut config.log_upload;
+    if log_upload.is_enabled() && log_upload.backend != UploadBackendKind::Local {
+        if let Err(reason) = check_url(&log_upload.endpoint, &["http", "https"]) {
+            issues.push(ConfigIssue {
+                field: "log_upload.endpoint".to_string(),
+                problem: ConfigProblem::InvalidUrl {
+                    value: log_upload.endpoint.clone(),
+                    reason,
+                },
+                recovered: true,
+            });
+            log_upload.endpoint.clear();
+        }
+    }
+    if !log_upload.recipient.is_empty() {
+        if let Err(reason) = age::x25519::Recipient::from_str(log_upload.recipient.trim()) {
+            issues.push(ConfigIssue {
+                field: "log_upload.recipient".to_string(),
+                problem: ConfigProblem::InvalidRecipient {
+                    value: log_upload.recipient.clone(),
+                    reason: reason.to_string(),
+                },
+                recovered: true,
+            });
+            // 不能退回明文上传，直接停用
+            log_upload.endpoint.clear();
+            log_upload.directory.clear();
+        }
+    }
+    let log_level = &mut config.log_level;
+    if LogLevel::from_str(&log_level.level).is_err() {
+        issues.push(ConfigIssue {
+            field: "log_level.level".to_string(),
+            problem: ConfigProblem::InvalidLogLevel(log_level.level.clone()),
+            recovered: true,
+        });
+        log_level.level = "info".to_string();
+    }
+    log_level.sources.retain(|source, level| {
+        let problem = if !LOG_SOURCES.contains(&source.as_str()) {
+            ConfigProblem::UnknownLogSource(source.clone())
+        } else if LogLevel::from_str(level).is_err() {
+            ConfigProblem::InvalidLogLevel(level.clone())
+        } else {
+            return true;
+        };
+        issues.push(ConfigIssue {
+            field: format!("log_level.sources.{}", source),
+            problem,
+            recovered: true,
+        });
+        false
+    });
+    log_level.targets.retain(|target, level| {
+        if LogLevel::from_str(level).is_ok() {
+            return true;
+        }
+        issues.push(ConfigIssue {
+            field: format!("log_level.targets.{}", target),
+            problem: ConfigProblem::InvalidLogLevel(level.clone()),
+            recovered: true,
+        });
+        false
+    });
+    let syslog = &mut config.syslog;
+    if syslog.is_enabled() && !is_valid_address(&syslog.address) {
+        issues.push(ConfigIssue {
+            field: "syslog.address".to_string(),
+            problem: ConfigProblem::InvalidAddress(syslog.address.clone()),
+            recovered: true,
+        });
+        syslog.address.clear();
+    }
+    if syslog.facility > 23 {
+        issues.push(ConfigIssue {
+            field: "syslog.facility".to_string(),
+            problem: ConfigProblem::InvalidFacility(syslog.facility),
+            recovered: true,
+        });
+        syslog.facility = 1;
+    }
+    if LogLevel::from_str(&syslog.min_level).is_err() {
+        issues.push(ConfigIssue {
+            field: "syslog.min_level".to_string(),
+            problem: ConfigProblem::InvalidLogLevel(syslog.min_level.clone()),
+            recovered: true,
+        });
+        syslog.min_level = "info".to_string();
+    }
+    let otlp = &mut config.otlp;
+    if !otlp.endpoint.is_empty() {
+        if let Err(reason) = check_url(&otlp.endpoint, &["http", "https"]) {
+            issues.push(ConfigIssue {
+                field: "otlp.endpoint".to_string(),
+                problem: ConfigProblem::InvalidUrl {
+                    value: otlp.endpoint.clone(),
+                    reason,
+                },
+                recovered: true,
+            });
+            otlp.endpoint.clear();
+        }
+    }
+    otlp.headers.retain(|name, value| {
+        let reason = match (HeaderName::from_str(name), HeaderValue::from_str(value)) {
+            (Err(e), _) => e.to_string(),
+            (_, Err(e)) => e.to_string(),
+            _ => return true,
+        };
+        issues.push(ConfigIssue {
+            field: format!("otlp.headers.{}", name),
+            problem: ConfigProblem::InvalidHeader {
+                name: name.clone(),
+                reason,
+            },
+            recovered: true,
+        });
+        false
+    });
+    if LogLevel::from_str(&otlp.min_level).is_err() {
+        issues.push(ConfigIssue {
+            field: "otlp.min_level".to_string(),
+            problem: ConfigProblem::InvalidLogLevel(otlp.min_level.clone()),
+            recovered: true,
+        });
+        otlp.min_level = "info".to_string();
+    }
+    // 无效的规则在脱敏时会被跳过，这里只负责提示
+    for (index, rule) in config.redaction.rules.iter().enumerate() {
+        if let Err(reason) = check_redaction_pattern(&rule.pattern) {
+            issues.push(ConfigIssue {
+                field: format!("redaction.rules[{}].pattern", index),
+                problem: ConfigProblem::InvalidPattern {
+                    value: rule.pattern.clone(),
+                    reason,
+                },
+                recovered: true,
+            });
+        }
+    }
+    if bundled_secret {
+        issues.push(ConfigIssue {
+            field: "log_upload.api_key".to_string(),
+            problem: ConfigProblem::BundledSecret,
+            recovered: true,
+        });
+    }
+
+    issues
+}
+
+fn is_valid_scheme(scheme: &str) -> bool {
+    let mut chars = scheme.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        && !["http", "https", "file"].contains(&scheme.to_ascii_lowercase().as_str())
+}
+
+/// `host:port`, with IPv6 hosts in brackets.
+fn is_valid_address(address: &str) -> bool {
+    address
+        .rsplit_once(':')
+        .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok_and(|port| port != 0))
+}
+
+fn check_url(value: &str, schemes: &[&str]) -> Result<(), String> {
+    let url = Url::from_str(value).map_err(|e| e.to_string())?;
+    if !schemes.contains(&url.scheme()) {
+        return Err(format!("协议只能是 {}", schemes.join(" / ")));
+    }
+    Ok(())
+}
+
+/// Logs the issues and shows them in a native dialog. Returns `true` when any issue is fatal;
+/// the app then exits once the dialog is dismissed.
+pub fn report_config_issues(app: &AppHandle, issues: &[ConfigIssue]) -> bool {
+    if issues.is_empty() {
+        return false;
+    }
+
+    for issue in issues {
+        let level = if issue.recovered { "WARN" } else { "ERROR" };
+        add_log_entry(level, &format!("配置校验: {}", issue));
+    }
+
+    let fatal = issues.iter().any(|issue| !issue.recovered);
+    let details = issues
+        .iter()
+        .map(|issue| format!("• {}", issue))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let dialog = app.dialog().message(details).title(if fatal {
+        "配置错误，应用无法启动"
+    } else {
+        "配置存在问题，已使用默认值"
+    });
+
+    if fatal {
+        let app_handle = app.clone();
+        dialog
+            .kind(MessageDialogKind::Error)
+            .show(move |_| app_handle.exit(1));
+    } else {
+        dialog.kind(MessageDialogKind::Warning).show(|_| {});
+    }
+
+    fatal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::config::{RedactionRule, WindowConfig};
+
+    fn valid_config() -> PakeConfig {
+        PakeConfig {
+            windows: vec![WindowConfig {
+                url: "https://example.com".to_string(),
+                ..WindowConfig::default()
+            }],
+            ..PakeConfig::default()
+        }
+    }
+
+    fn check(config: &mut PakeConfig) -> Vec<ConfigIssue> {
+        check_config(config, |_| true, false)
+    }
+
+    fn fields(issues: &[ConfigIssue]) -> Vec<(&str, bool)> {
+        issues
+            .iter()
+            .map(|issue| (issue.field.as_str(), issue.recovered))
+            .collect()
+    }
+
+    #[test]
+    fn accepts_a_valid_config() {
+        assert!(check(&mut valid_config()).is_empty());
+    }
+
+    #[test]
+    fn empty_windows_are_fatal() {
+        let mut config = PakeConfig {
+            windows: Vec::new(),
+            ..valid_config()
+        };
+        let issues = check(&mut config);
+        assert_eq!(fields(&issues), [("windows", false)]);
+        assert!(matches!(issues[0].problem, ConfigProblem::NoWindows));
+    }
+
+    #[test]
+    fn bad_window_url_is_fatal() {
+        for url in ["not a url", "ftp://example.com", ""] {
+            let mut config = valid_config();
+            config.windows[0].url = url.to_string();
+            assert_eq!(fields(&check(&mut config)), [("windows[0].url", false)], "{}", url);
+        }
+
+        let mut config = valid_config();
+        config.windows[0].url_type = "local".to_string();
+        config.windows[0].url = " ".to_string();
+        assert_eq!(fields(&check(&mut config)), [("windows[0].url", false)]);
+
+        let mut config = valid_config();
+        config.windows[0].url_type = "remote".to_string();
+        assert_eq!(fields(&check(&mut config)), [("windows[0].url_type", true)]);
+        assert_eq!(config.windows[0].url_type, "web");
+    }
+
+    #[test]
+    fn bad_size_falls_back_to_default() {
+        let mut config = valid_config();
+        config.windows[0].width = 0.0;
+        config.windows[0].height = f64::NAN;
+        assert_eq!(
+            fields(&check(&mut config)),
+            [("windows[0].width", true), ("windows[0].height", true)]
+        );
+        assert_eq!(config.windows[0].width, DEFAULT_WINDOW_WIDTH);
+        assert_eq!(config.windows[0].height, DEFAULT_WINDOW_HEIGHT);
+    }
+
+    #[test]
+    fn bad_shortcut_is_cleared() {
+        let mut config = valid_config();
+        config.windows[0].activation_shortcut = "CmdOrControl+Shift+P".to_string();
+        assert!(check(&mut config).is_empty());
+
+        config.windows[0].activation_shortcut = "Ctrl+NoSuchKey".to_string();
+        assert_eq!(
+            fields(&check(&mut config)),
+            [("windows[0].activation_shortcut", true)]
+        );
+        assert!(config.windows[0].activation_shortcut.is_empty());
+    }
+
+    #[test]
+    fn bad_proxy_is_cleared() {
+        let mut config = valid_config();
+        config.proxy_url = "socks5://127.0.0.1:1080".to_string();
+        assert!(check(&mut config).is_empty());
+
+        config.proxy_url = "https://127.0.0.1:1080".to_string();
+        assert_eq!(fields(&check(&mut config)), [("proxy_url", true)]);
+        assert!(config.proxy_url.is_empty());
+    }
+
+    #[test]
+    fn missing_tray_icon_is_cleared() {
+        let mut config = valid_config();
+        config.system_tray_path = "icons/missing.png".to_string();
+        let issues = check_config(&mut config, |_| false, false);
+        assert_eq!(fields(&issues), [("system_tray_path", true)]);
+        assert!(config.system_tray_path.is_empty());
+    }
+
+    #[test]
+    fn bad_log_levels_fall_back_or_are_dropped() {
+        let mut config = valid_config();
+        config.log_level.level = "verbose".to_string();
+        config.log_level.sources.insert("rust".to_string(), "debug".to_string());
+        config.log_level.sources.insert("webview".to_string(), "loud".to_string());
+        config.log_level.sources.insert("disk".to_string(), "info".to_string());
+        config.log_level.targets.insert("reqwest".to_string(), "warn".to_string());
+        config.log_level.targets.insert("hyper".to_string(), "quiet".to_string());
+        assert_eq!(
+            fields(&check(&mut config)),
+            [
+                ("log_level.level", true),
+                ("log_level.sources.disk", true),
+                ("log_level.sources.webview", true),
+                ("log_level.targets.hyper", true),
+            ]
+        );
+        assert_eq!(config.log_level.level, "info");
+        assert_eq!(config.log_level.sources.keys().collect::<Vec<_>>(), ["rust"]);
+        assert_eq!(config.log_level.targets.keys().collect::<Vec<_>>(), ["reqwest"]);
+    }
+
+    #[test]
+    fn bad_syslog_settings_are_reset() {
+        let mut config = valid_config();
+        config.syslog.address = "[::1]:514".to_string();
+        assert!(check(&mut config).is_empty());
+
+        config.syslog.address = "localhost:0".to_string();
+        config.syslog.facility = 24;
+        config.syslog.min_level = "none".to_string();
+        assert_eq!(
+            fields(&check(&mut config)),
+            [
+                ("syslog.address", true),
+                ("syslog.facility", true),
+                ("syslog.min_level", true),
+            ]
+        );
+        assert!(!config.syslog.is_enabled());
+        assert_eq!(config.syslog.facility, 1);
+        assert_eq!(config.syslog.min_level, "info");
+    }
+
+    #[test]
+    fn bad_otlp_settings_are_reset() {
+        let mut config = valid_config();
+        config.otlp.endpoint = "grpc://localhost:4317".to_string();
+        config.otlp.headers.insert("x-token".to_string(), "secret".to_string());
+        config.otlp.headers.insert("bad header".to_string(), "1".to_string());
+        config.otlp.headers.insert("x-line".to_string(), "a\nb".to_string());
+        config.otlp.min_level = "none".to_string();
+        assert_eq!(
+            fields(&check(&mut config)),
+            [
+                ("otlp.endpoint", true),
+                ("otlp.headers.bad header", true),
+                ("otlp.headers.x-line", true),
+                ("otlp.min_level", true),
+            ]
+        );
+        assert!(config.otlp.endpoint.is_empty());
+        assert_eq!(config.otlp.headers.keys().collect::<Vec<_>>(), ["x-token"]);
+        assert_eq!(config.otlp.min_level, "info");
+    }
+
+    #[test]
+    fn bad_redaction_pattern_is_reported_but_kept() {
+        let mut config = valid_config();
+        config.redaction.rules = vec![
+            RedactionRule {
+                name: "order".to_string(),
+                pattern: r"order-\d+".to_string(),
+                replacement: "[ORDER]".to_string(),
+            },
+            RedactionRule {
+                name: "broken".to_string(),
+                pattern: "(unclosed".to_string(),
+                replacement: "[REDACTED]".to_string(),
+            },
+        ];
+        assert_eq!(
+            fields(&check(&mut config)),
+            [("redaction.rules[1].pattern", true)]
+        );
+        assert_eq!(config.redaction.rules.len(), 2);
+    }
+
+    #[test]
+    fn bundled_secret_is_a_warning() {
+        let issues = check_config(&mut valid_config(), |_| true, true);
+        assert_eq!(fields(&issues), [("log_upload.api_key", true)]);
+        assert!(matches!(issues[0].problem, ConfigProblem::BundledSecret));
+    }
+}
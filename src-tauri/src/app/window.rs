@@ -1,26 +1,93 @@
-use crate::app::config::PakeConfig;
+use crate::app::config::{window_label, PakeConfig, WindowConfig};
+use crate::app::inject::load_inject_scripts;
+use crate::app::validate::{ConfigIssue, ConfigProblem};
 use crate::util::get_data_dir;
-use std::{path::PathBuf, str::FromStr};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 use tauri::{App, Config, Url, WebviewUrl, WebviewWindow, WebviewWindowBuilder};
 
+#[cfg(target_os = "macos")]
+use crate::app::profile::data_store_identifier;
 #[cfg(target_os = "macos")]
 use tauri::{Theme, TitleBarStyle};
 
-pub fn set_window(app: &mut App, config: &PakeConfig, tauri_config: &Config) -> WebviewWindow {
-    let package_name = tauri_config.clone().product_name.unwrap();
-    let _data_dir = get_data_dir(app.handle(), package_name);
+/// Builds every configured window, stopping at the first one that cannot be created.
+pub fn set_window(
+    app: &mut App,
+    config: &PakeConfig,
+    tauri_config: &Config,
+    profile: Option<&str>,
+) -> Result<Vec<WebviewWindow>, ConfigIssue> {
+    let issue = |field: String, problem| ConfigIssue {
+        field,
+        problem,
+        recovered: false,
+    };
+
+    // validate_config 已经报告过，这里只是不再 panic
+    if config.windows.is_empty() {
+        return Err(issue("windows".to_string(), ConfigProblem::NoWindows));
+    }
+
+    let package_name = tauri_config
+        .product_name
+        .as_deref()
+        .ok_or_else(|| issue("productName".to_string(), ConfigProblem::MissingProductName))?;
+    let mut data_dir = get_data_dir(app.handle(), package_name)
+        .map_err(|e| issue("productName".to_string(), ConfigProblem::DataDir(e)))?;
+
+    // 每个环境使用独立的webview数据目录，避免不同环境的会话混用
+    if let Some(profile) = profile {
+        data_dir = data_dir.join("profiles").join(profile);
+        std::fs::create_dir_all(&data_dir).map_err(|e| {
+            issue(
+                format!("profiles.{}", profile),
+                ConfigProblem::DataDir(format!("无法创建目录 {}: {}", data_dir.display(), e)),
+            )
+        })?;
+    }
 
-    let window_config = config
+    let inject_scripts = load_inject_scripts(app.handle(), config);
+
+    config
         .windows
-        .first()
-        .expect("At least one window configuration is required");
+        .iter()
+        .enumerate()
+        .map(|(index, window_config)| {
+            build_window(
+                app,
+                config,
+                window_config,
+                &window_label(index),
+                &data_dir,
+                profile,
+                &inject_scripts,
+            )
+            .map_err(|problem| issue(format!("windows[{}]", index), problem))
+        })
+        .collect()
+}
 
+fn build_window(
+    app: &mut App,
+    config: &PakeConfig,
+    window_config: &WindowConfig,
+    label: &str,
+    _data_dir: &Path,
+    _profile: Option<&str>,
+    inject_scripts: &[String],
+) -> Result<WebviewWindow, ConfigProblem> {
     let user_agent = config.user_agent.get();
 
     let url = match window_config.url_type.as_str() {
-        "web" => WebviewUrl::App(window_config.url.parse().unwrap()),
         "local" => WebviewUrl::App(PathBuf::from(&window_config.url)),
-        _ => panic!("url type can only be web or local"),
+        // url_type has been checked by `validate_config`, anything else is a web URL.
+        _ => WebviewUrl::External(Url::parse(&window_config.url).map_err(|e| ConfigProblem::InvalidUrl {
+            value: window_config.url.clone(),
+            reason: e.to_string(),
+        })?),
     };
 
     let config_script = format!(
@@ -28,7 +95,7 @@ pub fn set_window(app: &mut App, config: &PakeConfig, tauri_config: &Config) ->
         serde_json::to_string(&window_config).unwrap()
     );
 
-    let mut window_builder = WebviewWindowBuilder::new(app, "pake", url)
+    let mut window_builder = WebviewWindowBuilder::new(app, label, url)
         .title("")
         .visible(false)
         .user_agent(user_agent)
@@ -43,9 +110,12 @@ pub fn set_window(app: &mut App, config: &PakeConfig, tauri_config: &Config) ->
         .initialization_script(include_str!("../inject/style.js"))
         .initialization_script(include_str!("../inject/custom.js"));
 
-    if !config.proxy_url.is_empty() {
-        window_builder =
-            window_builder.proxy_url(Url::from_str(config.proxy_url.as_str()).unwrap());
+    for script in inject_scripts {
+        window_builder = window_builder.initialization_script(script);
+    }
+
+    if let Ok(proxy_url) = Url::from_str(config.proxy_url.as_str()) {
+        window_builder = window_builder.proxy_url(proxy_url);
     }
 
     #[cfg(target_os = "macos")]
@@ -60,14 +130,21 @@ pub fn set_window(app: &mut App, config: &PakeConfig, tauri_config: &Config) ->
         if window_config.dark_mode {
             window_builder = window_builder.theme(Some(Theme::Dark));
         }
+
+        // WKWebView has no data directory, separate profiles by data store instead (macOS 14+).
+        if let Some(profile) = _profile {
+            window_builder = window_builder.data_store_identifier(data_store_identifier(profile));
+        }
     }
 
     #[cfg(not(target_os = "macos"))]
     {
         window_builder = window_builder
-            .data_directory(_data_dir)
+            .data_directory(_data_dir.to_path_buf())
             .title(app.package_info().name.clone());
     }
 
-    window_builder.build().expect("Failed to build window")
+    window_builder
+        .build()
+        .map_err(|e| ConfigProblem::WindowBuild(e.to_string()))
 }
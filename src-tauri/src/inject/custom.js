@@ -430,6 +430,7 @@ const LOG_CONFIG = {
 
   MAX_NETWORK_LOGS: 2500,      // 网络请求日志最大条数，超出时删除旧日志
   REFRESH_INTERVAL: 2000,      // 日志显示刷新间隔（毫秒）
+  MAX_TAILED_LOGS: 1000,       // 运行日志窗口最多显示的条数
   CLICK_RESET_TIMEOUT: 2000,   // 版本号点击计数重置超时时间（毫秒）
   REQUIRED_CLICKS: 3           // 打开日志面板所需的版本号点击次数
 };
@@ -440,6 +441,9 @@ let versionClickCount = 0;
 let versionClickTimer = null;
 let currentLogTab = 'app';
 let logRefreshInterval = null;
+let unlistenLogStream = null;
+let tailedAppLogs = [];
+let droppedLogCount = 0;
 let dbInstance = null;
 
 // IndexedDB 配置
@@ -674,9 +678,62 @@ function showLogWindow() {
   `;
 
   uploadBtn.addEventListener('click', async () => {
+    // 上传过程中再次点击即取消
+    if (currentUploadId) {
+      window.__TAURI__.core.invoke('cancel_log_upload', { uploadId: currentUploadId }).catch(() => {});
+      return;
+    }
     await uploadLogs();
   });
 
+  // 导出日志按钮，保存为本地文件
+  const exportBtn = document.createElement('button');
+  exportBtn.textContent = '导出日志';
+  exportBtn.style.cssText = `
+    background: rgba(255, 255, 255, 0.15);
+    color: white;
+    border: none;
+    padding: 6px 12px;
+    border-radius: 6px;
+    cursor: pointer;
+    font-size: 12px;
+    transition: background 0.3s ease;
+  `;
+
+  exportBtn.addEventListener('click', exportLogs);
+
+  // 反馈问题按钮，问题描述和附件随日志一起上传
+  const feedbackBtn = document.createElement('button');
+  feedbackBtn.textContent = '反馈问题';
+  feedbackBtn.style.cssText = `
+    background: rgba(255, 255, 255, 0.15);
+    color: white;
+    border: none;
+    padding: 6px 12px;
+    border-radius: 6px;
+    cursor: pointer;
+    font-size: 12px;
+    transition: background 0.3s ease;
+  `;
+
+  feedbackBtn.addEventListener('click', () => {
+    if (!currentUploadId) showFeedbackForm();
+  });
+
+  // 未配置 log_upload 时禁用上传和反馈按钮
+  if (window.__TAURI__ && window.__TAURI__.core) {
+    window.__TAURI__.core.invoke('get_app_info').then(appInfo => {
+      if (!appInfo.log_upload_enabled) {
+        for (const btn of [uploadBtn, feedbackBtn]) {
+          btn.disabled = true;
+          btn.style.opacity = '0.5';
+          btn.style.cursor = 'not-allowed';
+          btn.title = '未配置日志上传地址';
+        }
+      }
+    }).catch(() => {});
+  }
+
   // 清空日志按钮
   const clearBtn = document.createElement('button');
   clearBtn.textContent = '清空日志';
@@ -715,9 +772,12 @@ function showLogWindow() {
       clearInterval(logRefreshInterval);
       logRefreshInterval = null;
     }
+    stopLogTail();
     logWindow.remove();
   });
 
+  controlGroup.appendChild(feedbackBtn);
+  controlGroup.appendChild(exportBtn);
   controlGroup.appendChild(uploadBtn);
   controlGroup.appendChild(clearBtn);
   controlGroup.appendChild(closeBtn);
@@ -780,15 +840,21 @@ function showLogWindow() {
         clearInterval(logRefreshInterval);
         logRefreshInterval = null;
       }
+      stopLogTail();
       logWindow.remove();
     }
   });
 
-  // 初始化日志显示
+  // 初始化日志显示，运行日志通过 pake://log 事件实时追加
   refreshLogDisplay();
+  startLogTail();
 
-  // 启动自动刷新
-  logRefreshInterval = setInterval(refreshLogDisplay, LOG_CONFIG.REFRESH_INTERVAL);
+  // 网络日志仍定时刷新；实时推送不可用时运行日志也回退到定时刷新
+  logRefreshInterval = setInterval(() => {
+    if (currentLogTab === 'network' || !unlistenLogStream) {
+      refreshLogDisplay();
+    }
+  }, LOG_CONFIG.REFRESH_INTERVAL);
 
   console.log('日志窗口已显示');
 }
@@ -855,6 +921,52 @@ function switchTab(tabId) {
   console.log(`切换到${tabId === 'app' ? '运行日志' : 'XHR/Fetch'}标签页`);
 }
 
+// 订阅 Rust 端推送的运行日志
+async function startLogTail() {
+  if (unlistenLogStream || !window.__TAURI__ || !window.__TAURI__.event) return;
+
+  const filter = { sources: ['rust', 'webview'] };
+  try {
+    // 先监听再订阅，之后拉取已有日志，按 seq 去重合并
+    const unlisten = await window.__TAURI__.event.listen('pake://log', (event) => {
+      appendTailedLogs(event.payload.entries, event.payload.dropped);
+    });
+    await window.__TAURI__.core.invoke('subscribe_logs', { filter });
+    unlistenLogStream = unlisten;
+
+    const page = await window.__TAURI__.core.invoke('get_logs', {
+      query: { ...filter, newest_first: true, limit: LOG_CONFIG.MAX_TAILED_LOGS }
+    });
+    appendTailedLogs(page.entries, 0);
+  } catch (error) {
+    console.error('订阅实时日志失败，改为定时刷新:', error);
+    stopLogTail();
+  }
+}
+
+function stopLogTail() {
+  if (unlistenLogStream) {
+    unlistenLogStream();
+    unlistenLogStream = null;
+    window.__TAURI__.core.invoke('unsubscribe_logs').catch(() => {});
+  }
+  tailedAppLogs = [];
+  droppedLogCount = 0;
+}
+
+function appendTailedLogs(entries, dropped) {
+  const known = new Set(tailedAppLogs.map(log => log.seq));
+  tailedAppLogs = tailedAppLogs
+    .concat(entries.filter(log => !known.has(log.seq)))
+    .sort((a, b) => a.seq - b.seq)
+    .slice(-LOG_CONFIG.MAX_TAILED_LOGS);
+  droppedLogCount += dropped;
+
+  if (currentLogTab === 'app') {
+    refreshLogDisplay();
+  }
+}
+
 // 刷新日志显示
 async function refreshLogDisplay() {
   const logDisplay = document.getElementById('log-display');
@@ -864,8 +976,14 @@ async function refreshLogDisplay() {
 
   if (currentLogTab === 'app') {
     try {
-      // 从IndexedDB获取最近的应用日志（最近24小时）
-      const recentAppLogs = await getRecentLogsFromDB(DB_CONFIG.stores.appLogs, 24);
+      // 优先由 Rust 端筛选最近的日志，包含 Rust 和页面控制台输出；不可用时回退到 IndexedDB
+      const recentAppLogs = unlistenLogStream
+        ? tailedAppLogs
+        : window.__TAURI__ && window.__TAURI__.core
+          ? (await window.__TAURI__.core.invoke('get_logs', {
+              query: { sources: ['rust', 'webview'], newest_first: true, limit: LOG_CONFIG.MAX_TAILED_LOGS }
+            })).entries
+          : await getRecentLogsFromDB(DB_CONFIG.stores.appLogs, 24);
 
       if (recentAppLogs.length === 0) {
         content = `<div style="text-align: center; padding: 40px; color: #666;">
@@ -876,6 +994,9 @@ async function refreshLogDisplay() {
         </div>`;
       } else {
         content = recentAppLogs.map(log => formatLogEntry(log, 'app')).join('');
+        if (droppedLogCount > 0) {
+          content = `<div style="padding: 4px 8px; color: #ffd43b; font-size: 11px;">显示速度跟不上，已跳过 ${droppedLogCount} 条日志</div>` + content;
+        }
       }
     } catch (error) {
       console.error('获取应用日志失败:', error);
@@ -916,6 +1037,16 @@ async function refreshLogDisplay() {
   //logDisplay.scrollTop = logDisplay.scrollHeight;
 }
 
+// 日志内容来自页面和网络请求，拼进 innerHTML 之前必须转义
+function escapeHtml(value) {
+  return String(value ?? '')
+    .replace(/&/g, '&amp;')
+    .replace(/</g, '&lt;')
+    .replace(/>/g, '&gt;')
+    .replace(/"/g, '&quot;')
+    .replace(/'/g, '&#39;');
+}
+
 // 格式化日志条目
 function formatLogEntry(log, type) {
   const timestamp = new Date(log.timestamp).toLocaleString('zh-CN', {
@@ -940,11 +1071,11 @@ function formatLogEntry(log, type) {
 
     return `<div style="margin-bottom: 2px; padding: 4px 8px; background: #252525; border-radius: 4px; border-left: 3px solid ${statusColor}; word-wrap: break-word; overflow-wrap: break-word; white-space: nowrap; overflow: hidden; text-overflow: ellipsis;">
       <span style="color: #888; font-size: 10px;">${timestamp}</span>
-      <span style="background: ${methodColor}; color: #000; padding: 1px 4px; border-radius: 2px; font-size: 9px; font-weight: bold; margin: 0 6px;">${log.method}</span>
-      <span style="color: ${statusColor}; font-weight: bold; font-size: 10px;">${log.status}</span>
-      <span style="color: #aaa; font-size: 10px; margin: 0 6px;">${log.duration}ms</span>
-      <span style="color: #e0e0e0; font-size: 11px;">${log.url}</span>
-      ${log.error ? ` <span style="color: #ff6b6b; font-size: 10px;">错误: ${log.error}</span>` : ''}
+      <span style="background: ${methodColor}; color: #000; padding: 1px 4px; border-radius: 2px; font-size: 9px; font-weight: bold; margin: 0 6px;">${escapeHtml(log.method)}</span>
+      <span style="color: ${statusColor}; font-weight: bold; font-size: 10px;">${escapeHtml(log.status)}</span>
+      <span style="color: #aaa; font-size: 10px; margin: 0 6px;">${escapeHtml(log.duration)}ms</span>
+      <span style="color: #e0e0e0; font-size: 11px;">${escapeHtml(log.url)}</span>
+      ${log.error ? ` <span style="color: #ff6b6b; font-size: 10px;">错误: ${escapeHtml(log.error)}</span>` : ''}
     </div>`;
   } else {
     const levelColor = {
@@ -956,8 +1087,9 @@ function formatLogEntry(log, type) {
 
     return `<div style="margin-bottom: 2px; padding: 4px 8px; background: #252525; border-radius: 4px; border-left: 3px solid ${levelColor}; word-wrap: break-word; overflow-wrap: break-word; white-space: nowrap; overflow: hidden; text-overflow: ellipsis;">
       <span style="color: #888; font-size: 10px;">${timestamp}</span>
-      <span style="color: ${levelColor}; font-size: 10px; font-weight: bold; text-transform: uppercase; margin: 0 6px;">${log.level}</span>
-      <span style="color: #e0e0e0; font-size: 11px; word-wrap: break-word;">${log.message}</span>
+      <span style="color: ${levelColor}; font-size: 10px; font-weight: bold; text-transform: uppercase; margin: 0 6px;">${escapeHtml(log.level)}</span>
+      ${log.target ? `<span style="color: #888; font-size: 10px; margin-right: 6px;">${escapeHtml(log.target)}</span>` : ''}
+      <span style="color: #e0e0e0; font-size: 11px; word-wrap: break-word;">${escapeHtml(log.message)}</span>
     </div>`;
   }
 }
@@ -1123,8 +1255,161 @@ function showUploadStatus(message, isComplete, isError = false) {
   }
 }
 
-// 上传日志功能
-async function uploadLogs() {
+// 正在进行的上传，用于显示进度和取消
+let currentUploadId = null;
+
+function showUploadProgress(progress) {
+  const uploadBtn = document.querySelector('button[data-original-text]');
+  if (!uploadBtn || !progress.total) return;
+  const percent = Math.floor((progress.bytes_sent / progress.total) * 100);
+  const part = progress.parts > 1 ? ` (${progress.part}/${progress.parts})` : '';
+  uploadBtn.textContent = `上传中 ${percent}%${part} · 点击取消`;
+  uploadBtn.disabled = false;
+}
+
+// 问题反馈表单
+function showFeedbackForm() {
+  if (document.getElementById('pake-feedback-form')) return;
+
+  const overlay = document.createElement('div');
+  overlay.id = 'pake-feedback-form';
+  overlay.style.cssText = `
+    position: fixed;
+    inset: 0;
+    background: rgba(0, 0, 0, 0.6);
+    display: flex;
+    justify-content: center;
+    align-items: center;
+    z-index: 1000001;
+    font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif;
+  `;
+
+  const form = document.createElement('form');
+  form.style.cssText = `
+    width: 420px;
+    background: #2d2d2d;
+    color: #e0e0e0;
+    border-radius: 10px;
+    padding: 20px;
+    display: flex;
+    flex-direction: column;
+    gap: 10px;
+    font-size: 13px;
+  `;
+  const fieldStyle = `
+    background: #1e1e1e;
+    color: #e0e0e0;
+    border: 1px solid #444;
+    border-radius: 6px;
+    padding: 6px 8px;
+    font-size: 13px;
+  `;
+  const buttonStyle = `
+    background: rgba(255, 255, 255, 0.15);
+    color: white;
+    border: none;
+    padding: 6px 12px;
+    border-radius: 6px;
+    cursor: pointer;
+    font-size: 12px;
+  `;
+
+  form.innerHTML = `
+    <div style="font-size: 16px; font-weight: 600;">反馈问题</div>
+    <select name="category" style="${fieldStyle}">
+      <option value="bug">功能异常</option>
+      <option value="crash">崩溃/白屏</option>
+      <option value="performance">卡顿/性能</option>
+      <option value="network">网络问题</option>
+      <option value="account">账号/登录</option>
+      <option value="other">其他</option>
+    </select>
+    <textarea name="description" rows="6" maxlength="5000" placeholder="请描述遇到的问题（必填）" style="${fieldStyle} resize: vertical;"></textarea>
+    <input name="contact" maxlength="200" placeholder="联系方式（可选）" style="${fieldStyle}">
+    <div style="display: flex; align-items: center; gap: 8px;">
+      <button type="button" data-action="attach" style="${buttonStyle}">添加截图/附件</button>
+      <span data-role="attachments" style="color: #888; font-size: 12px;">未选择附件</span>
+    </div>
+    <div style="display: flex; justify-content: flex-end; gap: 8px;">
+      <button type="button" data-action="cancel" style="${buttonStyle}">取消</button>
+      <button type="submit" style="${buttonStyle} background: rgba(76, 175, 80, 0.8);">提交</button>
+    </div>
+  `;
+
+  let attachments = [];
+  const attachmentLabel = form.querySelector('[data-role="attachments"]');
+  form.querySelector('[data-action="attach"]').addEventListener('click', async () => {
+    try {
+      const picked = await window.__TAURI__.core.invoke('pick_report_attachments');
+      attachments = attachments.concat(picked);
+      attachmentLabel.textContent = attachments.length
+        ? attachments.map(file => file.name).join(', ')
+        : '未选择附件';
+    } catch (error) {
+      showToastNotification(`选择附件失败: ${error}`, true);
+    }
+  });
+  form.querySelector('[data-action="cancel"]').addEventListener('click', () => overlay.remove());
+
+  form.addEventListener('submit', event => {
+    event.preventDefault();
+    const description = form.elements.description.value.trim();
+    if (!description) {
+      showToastNotification('请填写问题描述', true);
+      return;
+    }
+    overlay.remove();
+    uploadLogs({
+      description,
+      category: form.elements.category.value,
+      contact: form.elements.contact.value.trim() || null,
+      attachments: attachments.map(file => file.id),
+      page_url: window.location.href
+    });
+  });
+
+  overlay.appendChild(form);
+  document.body.appendChild(overlay);
+  form.elements.description.focus();
+}
+
+// 从IndexedDB获取最近2小时的日志数据，整理成上传和导出使用的格式
+async function collectLogsPayload() {
+  const recentAppLogs = await getRecentLogsFromDB(DB_CONFIG.stores.appLogs, 2);
+  const recentNetworkLogs = await getRecentLogsFromDB(DB_CONFIG.stores.networkLogs, 2);
+
+  // 日志时间范围，写入压缩包的 manifest.json
+  const timestamps = [...recentAppLogs, ...recentNetworkLogs]
+    .map(log => log.timestamp)
+    .filter(Number.isFinite);
+
+  return {
+    runtime: recentAppLogs.map(log => typeof log.message === 'string' ? log.message : JSON.stringify(log.message)),
+    xhr: recentNetworkLogs.map(log =>
+      `[${log.timestamp}] ${log.method} ${log.url} - Status: ${log.status} - Duration: ${log.duration}ms`
+    ),
+    from: timestamps.length ? timestamps.reduce((a, b) => Math.min(a, b)) : null,
+    to: timestamps.length ? timestamps.reduce((a, b) => Math.max(a, b)) : null
+  };
+}
+
+// 导出日志到本地文件，用于无法访问上传地址的环境
+async function exportLogs() {
+  if (!window.__TAURI__ || !window.__TAURI__.core) return;
+  try {
+    // IndexedDB 不可用时由 Rust 端使用自己的日志缓冲区
+    const logs = dbInstance ? await collectLogsPayload() : null;
+    const path = await window.__TAURI__.core.invoke('export_logs', { logs });
+    if (path) {
+      showToastNotification(`日志已导出: ${path}`);
+    }
+  } catch (error) {
+    showToastNotification(`导出失败: ${error}`, true);
+  }
+}
+
+// 上传日志功能，report 为问题反馈表单内容（可选）
+async function uploadLogs(report = null) {
   showUploadStatus('开始上传...', false);
 
   // 检查IndexedDB是否初始化
@@ -1135,17 +1420,9 @@ async function uploadLogs() {
   }
 
   try {
-    // 从IndexedDB获取最近2小时的日志数据
-    const recentAppLogs = await getRecentLogsFromDB(DB_CONFIG.stores.appLogs, 2);
-    const recentNetworkLogs = await getRecentLogsFromDB(DB_CONFIG.stores.networkLogs, 2);
-
-    // 收集日志数据
-    const runtimeLogs = recentAppLogs.map(log => typeof log.message === 'string' ? log.message : JSON.stringify(log.message));
-    const xhrLogs = recentNetworkLogs.map(log =>
-      `[${log.timestamp}] ${log.method} ${log.url} - Status: ${log.status} - Duration: ${log.duration}ms`
-    );
+    const logs = await collectLogsPayload();
 
-    console.log(`准备上传 ${runtimeLogs.length} 条应用日志和 ${xhrLogs.length} 条网络日志`);
+    console.log(`准备上传 ${logs.runtime.length} 条应用日志和 ${logs.xhr.length} 条网络日志`);
     showUploadStatus('正在准备上传...', false);
 
     // 检查 Tauri API 是否可用
@@ -1157,15 +1434,29 @@ async function uploadLogs() {
 
     // 调用 Rust 后端处理上传
     const { invoke } = window.__TAURI__.core;
-    const result = await invoke('handle_log_upload', {
-      logs: {
-        runtime: runtimeLogs,
-        xhr: xhrLogs
-      }
-    });
+    const uploadId = `upload-${Date.now()}`;
+    currentUploadId = uploadId;
+    const unlistenProgress = window.__TAURI__.event
+      ? await window.__TAURI__.event.listen('pake://upload-progress', event => {
+          if (event.payload.upload_id === uploadId) {
+            showUploadProgress(event.payload);
+          }
+        })
+      : null;
+    let result;
+    try {
+      result = await invoke('handle_log_upload', {
+        logs,
+        report,
+        uploadId
+      });
+    } finally {
+      currentUploadId = null;
+      if (unlistenProgress) unlistenProgress();
+    }
 
-    // 显示详细的成功信息
-    showUploadStatus('操作成功!', true, false);
+    // 显示详细的成功信息，反馈问题时附上报告编号
+    showUploadStatus(result.report_id ? `已提交，报告编号 ${result.report_id}` : '操作成功!', true, false);
 
     // 重置上传按钮
     resetUploadBtn();
@@ -1178,7 +1469,12 @@ async function uploadLogs() {
       error: error
     });
     const errorMessage = typeof error === 'string' ? error : (error.message || '未知错误');
-    showUploadStatus(`操作失败！`, true, true);
+    if (errorMessage === '上传已取消') {
+      showUploadStatus('已取消上传', true, false);
+      resetUploadBtn();
+      return;
+    }
+    showUploadStatus(report ? `提交失败: ${errorMessage}` : `操作失败！`, true, true);
     console.log('上传日志详细错误:', errorMessage);
     // 重置上传按钮
     resetUploadBtn();
@@ -1204,6 +1500,26 @@ function resetUploadBtn() {
     uploadBtn.disabled = false;
   }
 }
+// 待发送到 Rust 端的日志，批量发送以减少 IPC 调用
+let pendingRustLogs = [];
+let rustLogFlushTimer = null;
+
+function queueRustLog(entry) {
+  if (!window.__TAURI__ || !window.__TAURI__.core) return;
+  pendingRustLogs.push(entry);
+  if (!rustLogFlushTimer) {
+    rustLogFlushTimer = setTimeout(flushRustLogs, 500);
+  }
+}
+
+function flushRustLogs() {
+  rustLogFlushTimer = null;
+  const entries = pendingRustLogs;
+  pendingRustLogs = [];
+  // 失败时直接丢弃，避免 console.error 再次触发发送
+  window.__TAURI__.core.invoke('push_logs', { entries }).catch(() => {});
+}
+
 // 添加应用日志
 async function addAppLog(level, message) {
   const log = {
@@ -1211,6 +1527,7 @@ async function addAppLog(level, message) {
     level: level,
     message: message
   };
+  queueRustLog({ level, source: 'webview', message: String(message) });
 
   try {
     // 存储到 IndexedDB
@@ -1243,6 +1560,12 @@ async function addNetworkLog(method, url, status, duration, error = null) {
     duration: duration,
     error: error
   };
+  queueRustLog({
+    level: error || status >= 400 ? 'warn' : 'info',
+    source: 'network',
+    message: `${method} ${log.url} ${status} ${duration}ms${error ? ` ${error}` : ''}`,
+    fields: { method, url: log.url, status, duration }
+  });
 
   try {
     // 存储到 IndexedDB
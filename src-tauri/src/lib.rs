@@ -1,161 +1,10 @@
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
-use serde::{Deserialize, Serialize};
-use std::io::Write;
-use tauri::Manager;
-use zip::write::{FileOptions, ZipWriter};
-use chrono::Utc;
-use log::{error, info};
-use std::io::Cursor;
-use reqwest::Client;
-
-
-/// 从前端传递过来的日志数据结构
-#[derive(Deserialize)]
-struct LogsPayload {
-    runtime: Vec<String>,
-    xhr: Vec<String>,
-}
-
-/// 请求预签名URL的请求体结构
-#[derive(Serialize)]
-struct PresignedUrlRequest {
-    file_name: String,
-    content_type: String,
-    expiration: i32,
-    path: String,
-}
-
-/// 预签名URL的响应体结构
-#[derive(Deserialize)]
-struct PresignedUrlResponse {
-    presigned_url: String,
-    // 根据实际API响应添加其他字段
-}
+use tauri::{Manager, State};
 
 /// Tauri Command: 获取应用信息
 #[tauri::command]
-fn get_app_info() -> serde_json::Value {
-    let (_, tauri_config) = util::get_pake_config();
-
-    serde_json::json!({
-        "version": tauri_config.version.unwrap_or_else(|| "NaN".to_string()),
-        "product_name": tauri_config.product_name.unwrap_or_else(|| "NaN".to_string())
-    })
-}
-
-/// Tauri Command: 处理日志上传
-/// 接收前端发送的运行时日志和XHR日志，将它们打包成ZIP文件，
-/// 然后获取预签名URL，并将ZIP文件上传到该URL。
-#[tauri::command]
-async fn handle_log_upload(logs: LogsPayload) -> Result<String, tauri::Error> { // Return success message
-    // 硬编码的API配置
-    let presigned_url_api = "https://gateway.yitongweb.com/s3/api/get-presigned-url";
-    let api_key = "D1kSHode9yQM1syjd";
-
-    // 1. 创建 ZIP 文件内容
-    // 使用内存中的 Cursor 作为写入目标，避免直接写磁盘，提高效率并减少IO操作。
-    let mut zip_buffer = Cursor::new(Vec::new());
-    {
-        let mut zip = ZipWriter::new(&mut zip_buffer);
-        // 设置ZIP文件内条目的压缩选项，这里使用Deflate压缩算法。
-        let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
-
-        // 添加运行时日志到ZIP中，文件名为 runtime.log
-        zip.start_file("runtime.log", options)
-            .map_err(|e| tauri::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to start runtime.log in zip: {}", e))))?;
-        for log_entry in logs.runtime {
-            writeln!(zip, "{}", log_entry).map_err(|e| tauri::Error::Io(e))?;
-        }
-
-        // 添加 XHR 日志到ZIP中，文件名为 xhr.log
-        zip.start_file("xhr.log", options)
-            .map_err(|e| tauri::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to start xhr.log in zip: {}", e))))?;
-        for log_entry in logs.xhr {
-            writeln!(zip, "{}", log_entry).map_err(|e| tauri::Error::Io(e))?;
-        }
-        zip.finish().map_err(|e| tauri::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to finalize zip: {}", e))))?;
-    }
-    // 将Cursor中的ZIP数据转换为Vec<u8>
-    let zip_data = zip_buffer.into_inner();
-
-    // 2. 获取预签名 URL
-    // 创建一个 reqwest 客户端用于发送HTTP请求。此客户端支持阻塞式请求。
-    let client = Client::new();
-    // 生成带时间戳的文件名，格式为 pake_logs_YYYYMMDDHHMMSS.zip，确保文件名唯一性。
-    let timestamp = Utc::now().format("%Y%m%d%H%M%S");
-    let app_info = get_app_info();
-    log::info!("{:?}", app_info["product_name"]);
-    let product_name = app_info["product_name"].as_str().unwrap_or("pake_logs");
-    let file_name = format!("{}_{}.zip", product_name, timestamp);
-
-    // 构建获取预签名URL的请求体
-    let presigned_url_payload = PresignedUrlRequest {
-        file_name: file_name.clone(), // 上传到S3的文件名
-        content_type: "application/zip".to_string(), // 文件类型
-        expiration: 3600, // 预签名URL有效期，单位秒 (1小时)
-        path: "upload-log/".to_string(), // 文件在S3存储桶中的路径前缀
-    };
-
-    // 使用硬编码的API URL和Key
-    // 发送POST请求到预签名URL API，获取用于上传的URL
-    let res = client
-        .post(presigned_url_api)
-        .header("Content-Type", "application/json") // 设置请求头Content-Type
-        .header("X-API-Key", api_key) // 设置API密钥请求头
-        .json(&presigned_url_payload) // 将请求体序列化为JSON并发送
-        .send()
-        .await
-        .map_err(|e| tauri::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to request presigned URL: {}", e))))?; // 错误处理
-
-    // 检查获取预签名URL的请求是否成功
-    // 如果请求失败，记录错误并返回
-    let status = res.status();
-    if !status.is_success() {
-        let error_body = res.text().await.unwrap_or_else(|_| "Unknown error from presigned URL API".to_string());
-        error!("Failed to get presigned URL: {} - {}", status, error_body);
-            return Err(tauri::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, format!(
-                 "Failed to get presigned URL: {} - {}",
-                 status,
-                 error_body
-             ))));
-    }
-
-    // 解析预签名URL的JSON响应
-    let presigned_info = res
-        .json::<PresignedUrlResponse>() // 将响应体反序列化为PresignedUrlResponse结构体
-        .await
-        .map_err(|e| tauri::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to parse presigned URL response: {}", e))))?; // 错误处理
-
-    // 3. 上传 ZIP 文件到获取到的预签名 URL
-    // 使用HTTP PUT方法上传ZIP文件数据
-    let upload_res = client
-        .put(&presigned_info.presigned_url) // 目标URL为获取到的预签名URL
-        .header("Content-Type", "application/zip") // 设置Content-Type为application/zip
-        .body(zip_data) // 请求体为ZIP文件数据
-        .send()
-        .await
-        .map_err(|e| tauri::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to upload zip file: {}", e))))?; // 错误处理
-
-    // 检查文件上传是否成功
-    // 如果上传失败，记录错误并返回
-    let upload_status = upload_res.status();
-    if !upload_status.is_success() {
-        let error_body = upload_res.text().await.unwrap_or_else(|_| "Unknown error during upload".to_string());
-        error!(
-                "Failed to upload logs: {} - {}",
-                upload_status,
-                error_body
-            );
-            return Err(tauri::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, format!(
-                 "Failed to upload logs: {} - {}",
-                 upload_status,
-                 error_body
-             ))));
-    }
-
-    // 如果一切顺利，返回成功信息
-    info!("日志上传成功: {}", file_name);
-    Ok(format!("日志上传成功！文件名: {}", file_name))
+fn get_app_info(app: tauri::AppHandle, config: State<app::config::PakeConfig>) -> serde_json::Value {
+    util::app_info(app.config(), &config)
 }
 
 mod app;
@@ -169,69 +18,236 @@ use tauri_plugin_window_state::StateFlags;
 use std::time::Duration;
 
 use app::{
-    invoke::{download_file, download_file_by_binary, send_notification, get_logs, clear_logs},
+    args::{usage, AppArgs, ArgsError},
+    bundle::redacted_config,
+    config::window_label,
+    crash::{check_previous_crash, init_crash_dir, install_panic_hook},
+    deep_link::register_scheme,
+    invoke::{download_file, download_file_by_binary, send_notification, get_logs, clear_logs, push_logs},
+    launch::{handle_second_instance, open_targets, take_opened_files, PendingFiles},
+    log_export::export_logs,
+    log_file::{flush_log_file, init_log_file, list_log_files, read_log_file},
+    log_level::{configure_log_levels, set_log_level, LOG_SOURCES},
+    log_upload::{cancel_log_upload, handle_log_upload},
+    log_stream::{subscribe_logs, unsubscribe_logs},
+    logger::init_logger,
+    otlp::start_otlp,
+    profile::{initial_profile, ActiveProfile},
+    redact::configure_redaction,
+    report::pick_report_attachments,
     setup::{set_global_shortcut, set_system_tray},
+    syslog::start_syslog,
+    upload_queue::{discard_pending_upload, list_pending_uploads, retry_pending_upload, start_upload_queue},
+    validate::{report_config_issues, validate_config, ConfigIssue},
     window::set_window,
 };
-use util::get_pake_config;
+use util::load_pake_config;
+
+pub fn run_app(args: AppArgs) {
+    init_logger();
+
+    let loaded_config = load_pake_config();
+    let mut pake_config = loaded_config.pake_config;
+    let tauri_config = loaded_config.tauri_config;
+    install_panic_hook(
+        tauri_config.version.clone().unwrap_or_else(|| "NaN".to_string()),
+        &tauri_config.identifier,
+    );
+    let config_overlays = loaded_config.overlays;
+    let mut config_issues: Vec<ConfigIssue> = loaded_config
+        .parse_error
+        .map(ConfigIssue::parse)
+        .into_iter()
+        .collect();
+
+    let active_profile =
+        match initial_profile(&pake_config, &tauri_config.identifier, args.profile.as_deref()) {
+            Ok(profile) => profile,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }
+        };
+    if let Some(profile) = &active_profile {
+        pake_config.apply_profile(profile);
+    }
+    if let Some(url) = &args.url {
+        if let Some(window) = pake_config.windows.first_mut() {
+            window.url = url.clone();
+            window.url_type = "web".to_string();
+        }
+    }
+
+    if let Some(level) = &args.log_level {
+        pake_config.log_level.level = level.clone();
+    }
+    for (scope, level) in &args.log_level_overrides {
+        let source = scope.to_lowercase();
+        if LOG_SOURCES.contains(&source.as_str()) {
+            pake_config.log_level.sources.insert(source, level.clone());
+        } else {
+            pake_config.log_level.targets.insert(scope.clone(), level.clone());
+        }
+    }
+
+    if args.print_config {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&redacted_config(&pake_config))
+                .expect("Failed to serialize pake config")
+        );
+        return;
+    }
+
+    configure_log_levels(&pake_config.log_level);
+    configure_redaction(&pake_config.redaction);
 
-pub fn run_app() {
-    let (pake_config, tauri_config) = get_pake_config();
     let tauri_app = tauri::Builder::default();
 
     let show_system_tray = pake_config.show_system_tray();
-    let activation_shortcut = pake_config.windows[0].activation_shortcut.clone();
-    let init_fullscreen = pake_config.windows[0].fullscreen;
+    // The window state plugin tracks one set of flags for every window.
+    let init_fullscreen = pake_config.windows.iter().any(|window| window.fullscreen);
 
-    let window_state_plugin = WindowStatePlugin::default()
+    let mut window_state_plugin = WindowStatePlugin::default()
         .with_state_flags(if init_fullscreen {
             StateFlags::FULLSCREEN
         } else {
             // Prevent flickering on the first open.
             StateFlags::all() & !StateFlags::VISIBLE
-        })
-        .build();
+        });
+    if args.reset {
+        // The plugin reads its state file before our setup runs, so skip restoring instead.
+        for index in 0..pake_config.windows.len() {
+            window_state_plugin = window_state_plugin.skip_initial_state(&window_label(index));
+        }
+    }
+    let window_state_plugin = window_state_plugin.build();
 
     #[allow(deprecated)]
     tauri_app
-        .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
-            // 当尝试启动新实例时，聚焦到现有窗口
-            if let Some(window) = app.get_webview_window("main") {
-                let _ = window.set_focus();
-                let _ = window.unminimize();
-            }
+        .plugin(tauri_plugin_single_instance::init(|app, args, cwd| {
+            // 当尝试启动新实例时，聚焦到现有窗口并处理新实例的启动参数
+            handle_second_instance(app, args, cwd);
         }))
         .plugin(window_state_plugin)
         .plugin(tauri_plugin_oauth::init())
         .plugin(tauri_plugin_http::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_store::Builder::default().build())
         .invoke_handler(tauri::generate_handler![
             download_file,
             download_file_by_binary,
             send_notification,
             get_logs,
+            push_logs,
             clear_logs,
             handle_log_upload,
+            cancel_log_upload,
+            pick_report_attachments,
+            export_logs,
+            set_log_level,
             get_app_info,
+            take_opened_files,
+            list_log_files,
+            read_log_file,
+            subscribe_logs,
+            unsubscribe_logs,
+            list_pending_uploads,
+            retry_pending_upload,
+            discard_pending_upload,
         ])
         .setup(move |app| {
+            // 先打开日志文件，之前的日志会从缓冲区补写进去
+            match app.path().app_log_dir() {
+                Ok(log_dir) => {
+                    init_crash_dir(&log_dir);
+                    if let Err(e) = init_log_file(log_dir, &pake_config.log_file) {
+                        eprintln!("无法打开日志文件: {}", e);
+                    }
+                }
+                Err(e) => eprintln!("无法获取日志目录: {}", e),
+            }
+
             // 添加应用启动日志
             app::invoke::add_log_entry("INFO", "应用正在启动...");
 
-            let window = set_window(app, &pake_config, &tauri_config);
-            app::invoke::add_log_entry("INFO", "窗口设置完成");
+            for overlay in &config_overlays {
+                match &overlay.error {
+                    Some(e) => app::invoke::add_log_entry(
+                        "ERROR",
+                        &format!("配置覆盖文件无效，已忽略: {:?} - {}", overlay.path, e),
+                    ),
+                    None => app::invoke::add_log_entry(
+                        "INFO",
+                        &format!(
+                            "已应用配置覆盖文件: {:?}，覆盖字段: [{}]",
+                            overlay.path,
+                            overlay.overridden.join(", ")
+                        ),
+                    ),
+                }
+            }
+
+            if let Some(profile) = &active_profile {
+                app::invoke::add_log_entry("INFO", &format!("当前环境: {}", profile));
+            }
+            app.manage(ActiveProfile {
+                name: active_profile.clone(),
+                pinned: args.profile.is_some(),
+            });
+
+            if args.reset {
+                reset_app_data(app.app_handle(), &tauri_config);
+            }
+
+            config_issues.extend(validate_config(app.app_handle(), &mut pake_config));
+            if report_config_issues(app.app_handle(), &config_issues) {
+                // 不创建窗口，等待用户关闭错误对话框后退出
+                return Ok(());
+            }
+
+            app.manage(pake_config.clone());
+            app.manage(PendingFiles::default());
+            start_upload_queue(app.app_handle());
+            start_syslog(app.app_handle(), &pake_config.syslog);
+            start_otlp(app.app_handle(), &pake_config);
+
+            let windows = match set_window(app, &pake_config, &tauri_config, active_profile.as_deref()) {
+                Ok(windows) => windows,
+                Err(issue) => {
+                    // 同样等待用户关闭错误对话框后退出
+                    report_config_issues(app.app_handle(), &[issue]);
+                    return Ok(());
+                }
+            };
+            app::invoke::add_log_entry("INFO", &format!("窗口设置完成，共 {} 个窗口", windows.len()));
 
             set_system_tray(app.app_handle(), show_system_tray, &pake_config).unwrap();
             app::invoke::add_log_entry("INFO", "系统托盘设置完成");
 
-            set_global_shortcut(app.app_handle(), activation_shortcut).unwrap();
+            set_global_shortcut(app.app_handle(), pake_config.activation_shortcuts()).unwrap();
             app::invoke::add_log_entry("INFO", "全局快捷键设置完成");
 
+            register_scheme(app.app_handle(), &pake_config.deep_link, &tauri_config);
+
             // Prevent flickering on the first open.
-            window.show().unwrap();
+            for window in &windows {
+                window.show().unwrap();
+                #[cfg(any(debug_assertions, feature = "devtools"))]
+                if args.devtools {
+                    window.open_devtools();
+                }
+            }
             app::invoke::add_log_entry("INFO", "应用启动完成，窗口已显示");
 
+            if let Ok(cwd) = std::env::current_dir() {
+                open_targets(app.app_handle(), &args.targets, &cwd);
+            }
+
+            check_previous_crash(app.app_handle());
+
             Ok(())
         })
         .on_window_event(|_window, _event| {
@@ -253,21 +269,50 @@ pub fn run_app() {
                     
                     #[cfg(not(target_os = "macos"))]
                     {
-                        // 在非macOS平台上，窗口关闭即应用退出，清理webview缓存
+                        // 在非macOS平台上，最后一个窗口关闭即应用退出，清理webview缓存
                         let _ = api; // 显式使用api变量以避免警告
-                        clear_webview_data(_window.app_handle());
+                        if is_last_window(_window) {
+                            clear_webview_data(_window.app_handle());
+                        }
                     }
                 }
-                tauri::WindowEvent::Destroyed => {
-                    // 在所有平台上处理窗口销毁时清理webview缓存
+                tauri::WindowEvent::Destroyed if is_last_window(_window) => {
+                    // 在所有平台上处理最后一个窗口销毁时清理webview缓存
                     // 这确保了即使在macOS上通过其他方式退出应用时也会清理缓存
                     clear_webview_data(_window.app_handle());
                 }
                 _ => {}
             }
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_app, _event| {
+            if matches!(_event, tauri::RunEvent::Exit) {
+                // 退出前让写文件线程把队列写完
+                flush_log_file();
+            }
+            // macOS delivers scheme links and opened files as events instead of arguments.
+            #[cfg(target_os = "macos")]
+            if let tauri::RunEvent::Opened { urls } = _event {
+                let targets: Vec<String> = urls
+                    .into_iter()
+                    .map(|url| match url.to_file_path() {
+                        Ok(path) => path.to_string_lossy().into_owned(),
+                        Err(_) => url.to_string(),
+                    })
+                    .collect();
+                open_targets(_app, &targets, std::path::Path::new("/"));
+            }
+        });
+}
+
+// 其余窗口仍在使用共享的webview数据目录时不能清理
+fn is_last_window(window: &tauri::Window) -> bool {
+    window
+        .app_handle()
+        .webview_windows()
+        .keys()
+        .all(|label| label == window.label())
 }
 
 // 清理webview数据的函数
@@ -281,7 +326,12 @@ pub fn clear_webview_data(app_handle: &tauri::AppHandle) {
     
     for path in webview_data_paths {
         if path.exists() {
-            match fs::remove_dir_all(&path) {
+            let result = if path.is_dir() {
+                fs::remove_dir_all(&path)
+            } else {
+                fs::remove_file(&path)
+            };
+            match result {
                 Ok(_) => {
                     app::invoke::add_log_entry("INFO", &format!("已清理webview缓存: {:?}", path));
                 }
@@ -293,6 +343,38 @@ pub fn clear_webview_data(app_handle: &tauri::AppHandle) {
     }
 }
 
+// 清理webview数据、各环境的数据目录和保存的窗口状态（--reset）
+fn reset_app_data(app_handle: &tauri::AppHandle, tauri_config: &tauri::Config) {
+    use tauri_plugin_window_state::AppHandleExt;
+
+    clear_webview_data(app_handle);
+
+    let mut paths = Vec::new();
+    if let (Ok(config_dir), Some(package_name)) =
+        (app_handle.path().config_dir(), &tauri_config.product_name)
+    {
+        paths.push(config_dir.join(package_name));
+    }
+    if let Ok(app_config_dir) = app_handle.path().app_config_dir() {
+        paths.push(app_config_dir.join(app_handle.filename()));
+    }
+
+    for path in paths.into_iter().filter(|path| path.exists()) {
+        let result = if path.is_dir() {
+            std::fs::remove_dir_all(&path)
+        } else {
+            std::fs::remove_file(&path)
+        };
+        match result {
+            Ok(_) => app::invoke::add_log_entry("INFO", &format!("已重置: {:?}", path)),
+            Err(e) => app::invoke::add_log_entry(
+                "ERROR",
+                &format!("重置失败: {:?}, 错误: {}", path, e),
+            ),
+        }
+    }
+}
+
 // 获取不同平台的webview数据存储路径
 fn get_webview_data_paths(_app_handle: &tauri::AppHandle, _app_identifier: &str) -> Vec<std::path::PathBuf> {
     let mut paths = Vec::new();
@@ -317,9 +399,17 @@ fn get_webview_data_paths(_app_handle: &tauri::AppHandle, _app_identifier: &str)
     
     #[cfg(target_os = "linux")]
     {
-        // Linux: ~/.local/share/{app_identifier}
+        // Linux: ~/.local/share/{app_identifier}，日志目录也在其中，需要保留
         if let Ok(local_data_dir) = _app_handle.path().app_local_data_dir() {
-            paths.push(local_data_dir);
+            let log_dir = _app_handle.path().app_log_dir().ok();
+            if let Ok(entries) = std::fs::read_dir(&local_data_dir) {
+                paths.extend(
+                    entries
+                        .flatten()
+                        .map(|entry| entry.path())
+                        .filter(|path| Some(path) != log_dir.as_ref()),
+                );
+            }
         }
         // 也清理可能的缓存目录
         if let Some(home_dir) = dirs::home_dir() {
@@ -331,5 +421,23 @@ fn get_webview_data_paths(_app_handle: &tauri::AppHandle, _app_identifier: &str)
 }
 
 pub fn run() {
-    run_app()
+    let mut argv = std::env::args();
+    let program = argv.next().unwrap_or_else(|| "pake".to_string());
+
+    match AppArgs::parse(argv) {
+        Ok(args) => run_app(args),
+        Err(ArgsError::Help) => println!("{}", usage(&program)),
+        Err(ArgsError::Version) => {
+            let (_, tauri_config) = util::get_pake_config();
+            println!(
+                "{} {}",
+                tauri_config.product_name.unwrap_or_default(),
+                tauri_config.version.unwrap_or_default()
+            );
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, usage(&program));
+            std::process::exit(2);
+        }
+    }
 }
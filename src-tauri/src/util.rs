@@ -1,16 +1,164 @@
-use crate::app::config::PakeConfig;
+use crate::app::config::{PakeConfig, MAIN_WINDOW_LABEL};
+use serde_json::Value;
 use std::env;
 use std::path::PathBuf;
 use tauri::{AppHandle, Config, Manager, WebviewWindow};
 
+/// Environment variable pointing at a JSON file merged over the embedded pake.json.
+pub const CONFIG_OVERLAY_ENV: &str = "PAKE_CONFIG_OVERLAY";
+
+/// Overlay file looked up in the app config dir, e.g. `~/.config/<identifier>/pake.override.json`.
+pub const CONFIG_OVERLAY_FILE: &str = "pake.override.json";
+
+/// Outcome of applying one overlay file, reported at startup.
+#[derive(Debug)]
+pub struct ConfigOverlay {
+    pub path: PathBuf,
+    pub overridden: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// Effective configuration plus everything worth reporting about how it was loaded.
+pub struct LoadedConfig {
+    pub pake_config: PakeConfig,
+    pub tauri_config: Config,
+    pub overlays: Vec<ConfigOverlay>,
+    /// Set when the embedded pake.json could not be parsed and defaults were used instead.
+    pub parse_error: Option<String>,
+}
+
 pub fn get_pake_config() -> (PakeConfig, Config) {
+    let loaded = load_pake_config();
+    (loaded.pake_config, loaded.tauri_config)
+}
+
+/// Fields returned by the `get_app_info` command, also recorded in upload bundles.
+/// `tauri_config` is the one the app was built with, i.e. `app.config()`.
+pub fn app_info(tauri_config: &Config, config: &PakeConfig) -> Value {
+    serde_json::json!({
+        "version": tauri_config.version.as_deref().unwrap_or("NaN"),
+        "product_name": tauri_config.product_name.as_deref().unwrap_or("NaN"),
+        "log_upload_enabled": config.log_upload.is_enabled()
+    })
+}
+
+/// Like [`get_pake_config`], but also reports which overlay files were applied.
+pub fn load_pake_config() -> LoadedConfig {
+    let (embedded_config, tauri_config) = get_embedded_config();
+    let (embedded_config, parse_error) = match embedded_config {
+        Ok(config) => (config, None),
+        Err(e) => (PakeConfig::default(), Some(e.to_string())),
+    };
+    let mut merged = serde_json::to_value(&embedded_config).expect("Failed to serialize pake config");
+    let mut overlays = Vec::new();
+
+    for path in overlay_paths(&tauri_config) {
+        let mut overlay = ConfigOverlay {
+            path,
+            overridden: Vec::new(),
+            error: None,
+        };
+        let mut candidate = merged.clone();
+        match std::fs::read_to_string(&overlay.path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str::<Value>(&content).map_err(|e| e.to_string()))
+        {
+            Ok(patch) => {
+                merge_json(&mut candidate, patch, "", &mut overlay.overridden);
+                // Only keep the overlay if the merged result is still a valid PakeConfig.
+                match serde_json::from_value::<PakeConfig>(candidate.clone()) {
+                    Ok(_) => merged = candidate,
+                    Err(e) => {
+                        overlay.overridden.clear();
+                        overlay.error = Some(e.to_string());
+                    }
+                }
+            }
+            Err(e) => overlay.error = Some(e),
+        }
+        overlays.push(overlay);
+    }
+
+    let pake_config = serde_json::from_value(merged).unwrap_or(embedded_config);
+    LoadedConfig {
+        pake_config,
+        tauri_config,
+        overlays,
+        parse_error,
+    }
+}
+
+fn overlay_paths(tauri_config: &Config) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+
+    // Same location as `app.path().app_config_dir()`, which is not available before the app is built.
+    if let Some(config_dir) = dirs::config_dir() {
+        let path = config_dir
+            .join(&tauri_config.identifier)
+            .join(CONFIG_OVERLAY_FILE);
+        if path.exists() {
+            paths.push(path);
+        }
+    }
+
+    // The env var is applied last so it wins over the file in the config dir.
+    if let Ok(path) = env::var(CONFIG_OVERLAY_ENV) {
+        if !path.is_empty() {
+            paths.push(PathBuf::from(path));
+        }
+    }
+
+    paths
+}
+
+/// Deep-merges `patch` into `base`, recording the dotted path of every replaced value.
+/// Objects are merged key by key and arrays index by index, so `{"windows": [{"url": ".."}]}`
+/// only changes the first window's URL.
+fn merge_json(base: &mut Value, patch: Value, path: &str, overridden: &mut Vec<String>) {
+    match (base, patch) {
+        (Value::Object(base_map), Value::Object(patch_map)) => {
+            for (key, value) in patch_map {
+                let child_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                match base_map.get_mut(&key) {
+                    Some(base_value) => merge_json(base_value, value, &child_path, overridden),
+                    None => {
+                        base_map.insert(key, value);
+                        overridden.push(child_path);
+                    }
+                }
+            }
+        }
+        (Value::Array(base_items), Value::Array(patch_items)) => {
+            for (index, value) in patch_items.into_iter().enumerate() {
+                let child_path = format!("{}[{}]", path, index);
+                match base_items.get_mut(index) {
+                    Some(base_value) => merge_json(base_value, value, &child_path, overridden),
+                    None => {
+                        base_items.push(value);
+                        overridden.push(child_path);
+                    }
+                }
+            }
+        }
+        (base, patch) => {
+            if *base != patch {
+                *base = patch;
+                overridden.push(path.to_string());
+            }
+        }
+    }
+}
+
+pub fn get_embedded_config() -> (serde_json::Result<PakeConfig>, Config) {
     #[cfg(feature = "cli-build")]
-    let pake_config: PakeConfig = serde_json::from_str(include_str!("../.pake/pake.json"))
-        .expect("Failed to parse pake config");
+    let pake_config = serde_json::from_str(include_str!("../.pake/pake.json"));
 
     #[cfg(not(feature = "cli-build"))]
-    let pake_config: PakeConfig =
-        serde_json::from_str(include_str!("../pake.json")).expect("Failed to parse pake config");
+    let pake_config = serde_json::from_str(include_str!("../pake.json"));
 
     #[cfg(feature = "cli-build")]
     let tauri_config: Config = serde_json::from_str(include_str!("../.pake/tauri.conf.json"))
@@ -23,20 +171,24 @@ pub fn get_pake_config() -> (PakeConfig, Config) {
     (pake_config, tauri_config)
 }
 
-pub fn get_data_dir(app: &AppHandle, package_name: String) -> PathBuf {
-    {
-        let data_dir = app
-            .path()
-            .config_dir()
-            .expect("Failed to get data dirname")
-            .join(package_name);
+pub fn get_data_dir(app: &AppHandle, package_name: &str) -> Result<PathBuf, String> {
+    let data_dir = app
+        .path()
+        .config_dir()
+        .map_err(|e| e.to_string())?
+        .join(package_name);
 
-        if !data_dir.exists() {
-            std::fs::create_dir(&data_dir)
-                .unwrap_or_else(|_| panic!("Can't create dir {}", data_dir.display()));
-        }
-        data_dir
+    if !data_dir.exists() {
+        std::fs::create_dir(&data_dir)
+            .map_err(|e| format!("无法创建目录 {}: {}", data_dir.display(), e))?;
     }
+    Ok(data_dir)
+}
+
+/// The window built from the first `windows` entry, or any window if it was closed.
+pub fn get_main_window(app: &AppHandle) -> Option<WebviewWindow> {
+    app.get_webview_window(MAIN_WINDOW_LABEL)
+        .or_else(|| app.webview_windows().into_values().next())
 }
 
 pub fn show_toast(window: &WebviewWindow, message: &str) {
@@ -111,3 +263,165 @@ pub fn check_file_or_append(file_path: &str) -> String {
 
     new_path.to_string_lossy().into_owned()
 }
+
+/// Matches `text` against `pattern`, where `*` stands for any run of characters.
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text index it is currently matched up to.
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// [`wildcard_match`] as a regex for scripts that match in the webview, e.g.
+/// `https://*.example.com/*` -> `^https://.*\.example\.com/.*$`.
+pub fn wildcard_to_regex(pattern: &str) -> String {
+    let mut regex = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '.' | '+' | '?' | '(' | ')' | '[' | ']' | '{' | '}' | '^' | '$' | '|' | '\\' => {
+                regex.push('\\');
+                regex.push(c);
+            }
+            _ => regex.push(c),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcard_matches_literal_text() {
+        assert!(wildcard_match("/orders", "/orders"));
+        assert!(!wildcard_match("/orders", "/orders/1"));
+        assert!(!wildcard_match("/orders/1", "/orders"));
+    }
+
+    #[test]
+    fn wildcard_star_at_start_middle_and_end() {
+        assert!(wildcard_match("*.pdf", "report.pdf"));
+        assert!(!wildcard_match("*.pdf", "report.pdf.exe"));
+        assert!(wildcard_match("/orders/*/log", "/orders/42/log"));
+        assert!(wildcard_match("/orders/*/log", "/orders/42/x/log"));
+        assert!(!wildcard_match("/orders/*/log", "/orders/42/logs"));
+        assert!(wildcard_match("/orders/*", "/orders/"));
+        assert!(wildcard_match("/orders/*", "/orders/42?tab"));
+        assert!(!wildcard_match("/orders/*", "/order"));
+    }
+
+    #[test]
+    fn wildcard_backtracks_and_handles_empty_input() {
+        assert!(wildcard_match("*a*b", "xaxxab"));
+        assert!(wildcard_match("a**b", "ab"));
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("", ""));
+        assert!(!wildcard_match("", "a"));
+        assert!(wildcard_match("用户/*", "用户/张三"));
+    }
+
+    #[test]
+    fn wildcard_regex_agrees_with_wildcard_match() {
+        let cases = [
+            ("https://*.example.com/*", "https://app.example.com/orders?id=1"),
+            ("https://*.example.com/*", "https://example.com/"),
+            ("https://*.example.com/*", "https://appxexample.com/"),
+            ("https://x.com/a+b(c)/*", "https://x.com/a+b(c)/d"),
+            ("https://x.com/a+b(c)/*", "https://x.com/aab(c)/d"),
+            ("https://x.com/[id]$|^", "https://x.com/[id]$|^"),
+            ("https://x.com/?q=\\*", "https://x.com/?q=\\z"),
+            ("*a*b", "xaxxab"),
+            ("", ""),
+            ("", "a"),
+        ];
+        for (pattern, text) in cases {
+            let regex = regex::Regex::new(&wildcard_to_regex(pattern)).unwrap();
+            assert_eq!(
+                regex.is_match(text),
+                wildcard_match(pattern, text),
+                "{} ~ {}",
+                pattern,
+                text
+            );
+        }
+    }
+
+    fn merge(mut base: Value, patch: Value) -> (Value, Vec<String>) {
+        let mut overridden = Vec::new();
+        merge_json(&mut base, patch, "", &mut overridden);
+        overridden.sort();
+        (base, overridden)
+    }
+
+    #[test]
+    fn merge_replaces_nested_values_only() {
+        let (merged, overridden) = merge(
+            serde_json::json!({"proxy_url": "", "log_file": {"enabled": true, "max_files": 20}}),
+            serde_json::json!({"log_file": {"max_files": 5}}),
+        );
+        assert_eq!(
+            merged,
+            serde_json::json!({"proxy_url": "", "log_file": {"enabled": true, "max_files": 5}})
+        );
+        assert_eq!(overridden, ["log_file.max_files"]);
+    }
+
+    #[test]
+    fn merge_arrays_by_index() {
+        let (merged, overridden) = merge(
+            serde_json::json!({"windows": [{"url": "a", "width": 800}, {"url": "b"}]}),
+            serde_json::json!({"windows": [{"url": "c"}, {}, {"url": "d"}]}),
+        );
+        assert_eq!(
+            merged,
+            serde_json::json!({"windows": [{"url": "c", "width": 800}, {"url": "b"}, {"url": "d"}]})
+        );
+        assert_eq!(overridden, ["windows[0].url", "windows[2]"]);
+    }
+
+    #[test]
+    fn merge_records_new_keys_and_type_changes() {
+        let (merged, overridden) = merge(
+            serde_json::json!({"inject": [], "deep_link": {"scheme": "app"}}),
+            serde_json::json!({"inject": {"path": "x.js"}, "deep_link": {"hosts": ["a"]}}),
+        );
+        assert_eq!(
+            merged,
+            serde_json::json!({
+                "inject": {"path": "x.js"},
+                "deep_link": {"scheme": "app", "hosts": ["a"]}
+            })
+        );
+        assert_eq!(overridden, ["deep_link.hosts", "inject"]);
+    }
+
+    #[test]
+    fn merge_skips_unchanged_values() {
+        let base = serde_json::json!({"user_agent": {"macos": "ua"}, "windows": [{"url": "a"}]});
+        let (merged, overridden) = merge(base.clone(), base.clone());
+        assert_eq!(merged, base);
+        assert!(overridden.is_empty());
+    }
+}